@@ -0,0 +1,269 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rspotify::{http::HttpError, ClientError};
+use serde::{Deserialize, Serialize};
+use std::{fmt, fs, path::PathBuf, sync::Mutex, time::Duration};
+use tokio::sync::Mutex as AsyncMutex;
+use tracing::{info, warn};
+
+use crate::rate_limit::RateLimited;
+use crate::spotify_api::SpotifyApi;
+use crate::spotify_client::{SpotifyManager, TrackInfo};
+use crate::token_store;
+
+/// Delay between replays, doubled while Spotify stays unreachable
+pub const MIN_BACKOFF: Duration = Duration::from_secs(5);
+pub const MAX_BACKOFF: Duration = Duration::from_secs(2 * 60);
+
+const QUEUE_FILE_STEM: &str = "action_queue";
+
+/// A mutating action that can be deferred until Spotify is reachable
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueuedAction {
+    Like,
+    Unlike,
+}
+
+impl fmt::Display for QueuedAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            QueuedAction::Like => "like",
+            QueuedAction::Unlike => "unlike",
+        })
+    }
+}
+
+/// A like or unlike waiting in the queue
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingAction {
+    /// Bare track ID, not the `spotify:track:` URI
+    pub track_id: String,
+    pub action: QueuedAction,
+    pub queued_at: DateTime<Utc>,
+    /// Kept for the notification once the action is replayed
+    pub name: String,
+    pub artist: String,
+}
+
+impl PendingAction {
+    pub fn track_info(&self) -> TrackInfo {
+        let uri = format!("spotify:track:{}", self.track_id);
+        TrackInfo {
+            id: Some(uri.clone()),
+            name: self.name.clone(),
+            artist: self.artist.clone(),
+            uri: Some(uri),
+        }
+    }
+}
+
+/// Outcome of one replay run
+#[derive(Debug, Default)]
+pub struct ReplayReport {
+    pub applied: Vec<PendingAction>,
+    /// Dropped from the queue, with the reason
+    pub failed: Vec<(PendingAction, String)>,
+    /// Spotify was still unreachable or rate limited, the rest of the queue is kept
+    pub retry_later: bool,
+}
+
+/// Likes and unlikes that failed because Spotify was unreachable, persisted per profile
+///
+/// At most one action is kept per track: a newer one replaces the older, so
+/// liking and then unliking a track while offline replays as a single unlike.
+#[derive(Debug, Default)]
+pub struct ActionQueue {
+    /// `None` keeps the queue in memory only
+    path: Option<PathBuf>,
+    pending: Mutex<Vec<PendingAction>>,
+}
+
+impl ActionQueue {
+    /// Open the queue file of a profile in the cache directory
+    pub fn open(profile: &str) -> Result<Self> {
+        let path = token_store::cache_dir()?
+            .join(format!("{}.json", token_store::profile_key(QUEUE_FILE_STEM, profile)));
+        Self::open_at(path)
+    }
+
+    pub fn open_at(path: PathBuf) -> Result<Self> {
+        let pending = if path.exists() {
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read offline action queue at {}", path.display()))?;
+            serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse offline action queue at {}", path.display()))?
+        } else {
+            Vec::new()
+        };
+
+        let queue = Self { path: Some(path), pending: Mutex::new(pending) };
+        if !queue.is_empty() {
+            info!("📥 {} offline action(s) waiting to be replayed", queue.len());
+        }
+        Ok(queue)
+    }
+
+    /// Add an action, replacing any pending action for the same track
+    pub fn push(&self, action: PendingAction) -> Result<()> {
+        let mut pending = self.pending.lock().unwrap();
+        pending.retain(|queued| queued.track_id != action.track_id);
+        pending.push(action);
+        self.save(&pending)
+    }
+
+    /// Drop whatever is pending for a track
+    pub fn discard(&self, track_id: &str) -> Result<()> {
+        let mut pending = self.pending.lock().unwrap();
+        let before = pending.len();
+        pending.retain(|queued| queued.track_id != track_id);
+        if pending.len() == before {
+            return Ok(());
+        }
+        self.save(&pending)
+    }
+
+    /// Drop exactly this action, leaving a newer one for the same track in place
+    pub fn complete(&self, action: &PendingAction) -> Result<()> {
+        let mut pending = self.pending.lock().unwrap();
+        pending.retain(|queued| queued != action);
+        self.save(&pending)
+    }
+
+    /// Action pending for a track, if any
+    pub fn pending_for(&self, track_id: &str) -> Option<QueuedAction> {
+        self.pending.lock().unwrap()
+            .iter()
+            .find(|queued| queued.track_id == track_id)
+            .map(|queued| queued.action)
+    }
+
+    /// Oldest pending action
+    pub fn first(&self) -> Option<PendingAction> {
+        self.pending.lock().unwrap().first().cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Write the whole queue, through a temporary file so a crash never leaves it half written
+    fn save(&self, pending: &[PendingAction]) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_string_pretty(pending)?)
+            .context("Failed to write offline action queue")?;
+        fs::rename(&temp_path, path)
+            .context("Failed to replace offline action queue")?;
+        Ok(())
+    }
+}
+
+/// Whether a failed call never reached Spotify (no network, DNS, connection reset, timeout)
+pub fn is_offline(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| match cause.downcast_ref::<ClientError>() {
+        Some(ClientError::Http(http)) => matches!(http.as_ref(), HttpError::Client(_)),
+        _ => false,
+    })
+}
+
+/// Apply the manager's queued actions in order, locking the manager per action
+///
+/// Stops at the first action that fails because Spotify is still unreachable or
+/// rate limited; any other failure drops the action and is reported.
+pub async fn replay<A: SpotifyApi>(manager: &AsyncMutex<SpotifyManager<A>>) -> ReplayReport {
+    let mut report = ReplayReport::default();
+
+    loop {
+        let mut manager = manager.lock().await;
+        let queue = manager.action_queue();
+        let Some(pending) = queue.first() else {
+            break;
+        };
+
+        info!("📤 Replaying offline {} of {} - {}", pending.action, pending.name, pending.artist);
+        let result = manager.apply_queued(&pending).await;
+        drop(manager);
+
+        match result {
+            Ok(_) => report.applied.push(pending.clone()),
+            Err(e) if is_offline(&e) || e.downcast_ref::<RateLimited>().is_some() => {
+                info!("📴 Spotify still unavailable, keeping {} queued action(s): {}", queue.len(), e);
+                report.retry_later = true;
+                break;
+            }
+            Err(e) => {
+                warn!("❌ Dropping offline {} of {} - {}: {}", pending.action, pending.name, pending.artist, e);
+                report.failed.push((pending.clone(), e.to_string()));
+            }
+        }
+
+        // Successful actions already left the queue, failed ones have to be taken out
+        if let Err(e) = queue.complete(&pending) {
+            warn!("⚠️ Failed to update offline action queue: {}", e);
+            break;
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending(track_id: &str, action: QueuedAction) -> PendingAction {
+        PendingAction {
+            track_id: track_id.to_string(),
+            action,
+            queued_at: Utc::now(),
+            name: "Song".to_string(),
+            artist: "Artist".to_string(),
+        }
+    }
+
+    #[test]
+    fn newer_action_for_a_track_replaces_the_older() {
+        let queue = ActionQueue::default();
+
+        queue.push(pending("a", QueuedAction::Like)).unwrap();
+        queue.push(pending("b", QueuedAction::Like)).unwrap();
+        queue.push(pending("a", QueuedAction::Unlike)).unwrap();
+
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.first().unwrap().track_id, "b");
+        queue.discard("b").unwrap();
+        assert_eq!(queue.first().unwrap().action, QueuedAction::Unlike);
+    }
+
+    #[test]
+    fn queue_survives_reopening() {
+        let path = std::env::temp_dir().join(format!("action_queue_test_{}.json", std::process::id()));
+        let action = pending("a", QueuedAction::Like);
+
+        ActionQueue::open_at(path.clone()).unwrap().push(action.clone()).unwrap();
+        let reopened = ActionQueue::open_at(path.clone()).unwrap();
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(reopened.first(), Some(action));
+    }
+
+    #[test]
+    fn complete_keeps_newer_action_for_the_same_track() {
+        let queue = ActionQueue::default();
+        let like = pending("a", QueuedAction::Like);
+        queue.push(like.clone()).unwrap();
+        queue.push(pending("a", QueuedAction::Unlike)).unwrap();
+
+        queue.complete(&like).unwrap();
+
+        assert_eq!(queue.first().unwrap().action, QueuedAction::Unlike);
+    }
+}
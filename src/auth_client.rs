@@ -0,0 +1,415 @@
+use anyhow::{anyhow, Context, Result};
+use rspotify::{
+    model::{AdditionalType, CurrentPlaybackContext, CurrentlyPlayingContext, Device, Page, PrivateUser, RepeatState, SavedTrack, TrackId},
+    prelude::*,
+    sync::Mutex,
+    AuthCodePkceSpotify, AuthCodeSpotify, ClientResult, Config, Credentials, OAuth, Token,
+};
+use std::{collections::HashSet, sync::Arc};
+use tracing::{info, warn};
+use url::Url;
+
+use crate::config::AuthFlow;
+use crate::convergence::Step;
+use crate::oauth_callback::{self, AuthorizationRequest};
+use crate::spotify_api::SpotifyApi;
+use crate::token_lifecycle::TokenLifecycle;
+use crate::token_store::TokenStore;
+
+/// Dispatch a call to whichever rspotify client is active
+macro_rules! with_client {
+    ($self:expr, $client:ident => $body:expr) => {
+        match $self {
+            FlowClient::Code($client) => $body,
+            FlowClient::Pkce($client) => $body,
+        }
+    };
+}
+
+/// rspotify client for the configured authorization flow
+#[derive(Debug, Clone)]
+enum FlowClient {
+    /// Authorization code flow, authenticates with client ID and client secret
+    Code(AuthCodeSpotify),
+    /// Authorization code flow with PKCE, only needs the client ID
+    Pkce(AuthCodePkceSpotify),
+}
+
+/// rspotify-backed SpotifyApi, owning the interactive authorization against its token store
+pub struct AuthClient {
+    flow: FlowClient,
+    token_store: Arc<dyn TokenStore>,
+}
+
+impl AuthClient {
+    /// Build the client matching `flow`; the secret is ignored for PKCE
+    pub fn new(
+        flow: AuthFlow,
+        client_id: &str,
+        client_secret: &str,
+        oauth: OAuth,
+        config: Config,
+        token_store: Arc<dyn TokenStore>,
+    ) -> Self {
+        let flow = match flow {
+            AuthFlow::Code => {
+                let creds = Credentials::new(client_id, client_secret);
+                FlowClient::Code(AuthCodeSpotify::with_config(creds, oauth, config))
+            }
+            AuthFlow::Pkce => {
+                let creds = Credentials::new_pkce(client_id);
+                FlowClient::Pkce(AuthCodePkceSpotify::with_config(creds, oauth, config))
+            }
+        };
+
+        Self { flow, token_store }
+    }
+
+    /// Build the authorization URL; for PKCE this also generates the code verifier
+    fn get_authorize_url(&mut self) -> ClientResult<String> {
+        match &mut self.flow {
+            FlowClient::Code(client) => client.get_authorize_url(true),
+            FlowClient::Pkce(client) => client.get_authorize_url(None),
+        }
+    }
+
+    fn get_oauth(&self) -> &OAuth {
+        with_client!(&self.flow, client => client.get_oauth())
+    }
+
+    /// Replace the scopes requested by the next authorization
+    fn set_scopes(&mut self, scopes: HashSet<String>) {
+        with_client!(&mut self.flow, client => client.oauth.scopes = scopes)
+    }
+
+    /// Replace the state sent with the next authorization URL
+    fn set_state(&mut self, state: &str) {
+        with_client!(&mut self.flow, client => client.oauth.state = state.to_string())
+    }
+
+    /// Copy sharing the configuration and token store but with its own token,
+    /// so a login can run on it without touching the client in use
+    fn detached(&self) -> Self {
+        let mut flow = self.flow.clone();
+        with_client!(&mut flow, client => client.token = Arc::new(Mutex::new(None)));
+        Self { flow, token_store: Arc::clone(&self.token_store) }
+    }
+
+    fn get_token(&self) -> Arc<Mutex<Option<Token>>> {
+        with_client!(&self.flow, client => client.get_token())
+    }
+
+    /// Exchange an authorization code for tokens
+    async fn request_token(&self, code: &str) -> ClientResult<()> {
+        with_client!(&self.flow, client => client.request_token(code).await)
+    }
+
+    /// Ensure client is authenticated, handling token refresh automatically
+    pub async fn ensure_authenticated(&mut self) -> Result<()> {
+        // Try to load cached token first
+        match self.token_store.load() {
+            Ok(Some(token)) => {
+                info!("📁 Loaded cached token");
+
+                // Debug: Log token details (without exposing sensitive data)
+                info!("🔍 Token debug - access_token length: {}, refresh_token present: {}, expires_at: {:?}",
+                    token.access_token.len(),
+                    token.refresh_token.is_some(),
+                    token.expires_at);
+
+                if let Some(ref refresh_token) = token.refresh_token {
+                    info!("🔍 Refresh token length: {}", refresh_token.len());
+                } else {
+                    warn!("🔍 Refresh token is None");
+                }
+
+                // Check if we have both access and refresh tokens
+                if token.access_token.is_empty() {
+                    warn!("❌ Cached token is missing access token, re-authenticating...");
+                    self.authorize_interactively().await?;
+                    return Ok(());
+                }
+
+                if token.refresh_token.is_none() || token.refresh_token.as_ref().unwrap().is_empty() {
+                    warn!("❌ Cached token is missing refresh token, re-authenticating...");
+                    self.authorize_interactively().await?;
+                    return Ok(());
+                }
+
+                // The store only hands us the token, it still has to be set in the client
+                *self.get_token().lock().await.unwrap() = Some(token.clone());
+                info!("🔧 Token set in client internal state");
+
+                self.check_cached_token().await?;
+            }
+            Ok(None) => {
+                info!("🔐 No cached token found, starting initial authentication...");
+                self.authorize_interactively().await?;
+            }
+            Err(e) => {
+                // Usually a changed passphrase or machine ID, the file is still good with the right key
+                let location = self.token_store.location();
+                warn!("❌ Failed to read token cache at {}: {:#}", location, e);
+                return Err(e.context(format!(
+                    "Token cache at {} can't be read and was left untouched. \
+                     Fix the encryption key, or remove it to log in again",
+                    location
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check a cached token at startup without letting a network problem log the user out
+    ///
+    /// Refreshes only when the token expired or Spotify answers 401, and asks for a
+    /// new login only when that refresh is rejected. Anything else (offline, DNS,
+    /// 429, 5xx) starts with the cached token; the first request refreshes it as needed.
+    async fn check_cached_token(&mut self) -> Result<()> {
+        let lifecycle = TokenLifecycle::default();
+        let refreshed = match lifecycle.ensure_fresh(&*self).await {
+            Ok(()) => match self.current_user().await {
+                Ok(user) => {
+                    info!("✅ Token is valid for user: {}",
+                        user.display_name.unwrap_or_else(|| "Unknown".to_string()));
+                    return Ok(());
+                }
+                Err(e) if TokenLifecycle::is_unauthorized(&e) => lifecycle.refresh_after_unauthorized(&*self).await,
+                Err(e) => {
+                    warn!("📴 Couldn't check the cached token ({}), starting offline", e);
+                    return Ok(());
+                }
+            },
+            Err(e) => Err(e),
+        };
+        
+        match refreshed {
+            // Persisted by the token callback
+            Ok(()) => Ok(()),
+            Err(e) if TokenLifecycle::is_refresh_rejected(&e) => {
+                warn!("❌ Spotify rejected the refresh token ({:#}), need to re-authenticate", e);
+                self.authorize_interactively().await
+            }
+            Err(e) => {
+                warn!("📴 Couldn't refresh the cached token ({:#}), starting offline", e);
+                Ok(())
+            }
+        }
+    }
+
+    /// Run the browser authorization and exchange the code, keeping the stored token until replaced
+    async fn authorize_interactively(&mut self) -> Result<()> {
+        // Fresh state per attempt, so redirects from earlier attempts are rejected
+        let mut request = AuthorizationRequest::new();
+        self.set_state(request.state());
+
+        let url = self.get_authorize_url()?;
+        let redirect_uri = self.get_oauth().redirect_uri.clone();
+
+        let code = if oauth_callback::is_loopback_redirect(&redirect_uri) {
+            Self::receive_code_via_callback(&url, &redirect_uri, &mut request).await?
+        } else {
+            Self::receive_code_via_paste(&url, &mut request)?
+        };
+
+        // Exchange authorization code for tokens
+        self.request_token(&code).await
+            .context("Failed to exchange authorization code for tokens")?;
+
+        // Immediately check if we got a refresh token
+        match self.get_token().lock().await.unwrap().as_ref() {
+            Some(token) => {
+                info!("🔍 Token obtained - access_token length: {}, refresh_token present: {}",
+                    token.access_token.len(),
+                    token.refresh_token.is_some());
+
+                if token.refresh_token.is_none() {
+                    return Err(anyhow!("Authorization completed but no refresh token was provided by Spotify. This may be due to an app configuration issue."));
+                }
+
+                if let Some(ref refresh_token) = token.refresh_token {
+                    if refresh_token.is_empty() {
+                        return Err(anyhow!("Authorization completed but refresh token is empty."));
+                    }
+                    info!("✅ Valid refresh token obtained (length: {})", refresh_token.len());
+                }
+            }
+            None => {
+                return Err(anyhow!("Authorization completed but no token was set in client"));
+            }
+        }
+
+        // The token callback has already persisted the new token,
+        // verify the token was saved correctly by reading it back
+        match self.token_store.load() {
+            Ok(Some(token)) => {
+                let has_access = !token.access_token.is_empty();
+                let has_refresh = token.refresh_token.is_some() && !token.refresh_token.as_ref().unwrap().is_empty();
+                info!("Token verification: access_token={}, refresh_token={}", has_access, has_refresh);
+
+                if !has_access || !has_refresh {
+                    warn!("⚠️ Saved token is incomplete - this may cause re-authentication on next run");
+                }
+            }
+            Ok(None) => {
+                warn!("⚠️ No token found after saving - this may cause re-authentication on next run");
+            }
+            Err(e) => {
+                warn!("⚠️ Failed to verify saved token: {} - this may cause re-authentication on next run", e);
+            }
+        }
+
+        println!("✅ Authentication successful! Token cached for future use.");
+        println!("🎉 You'll never need to authenticate again (unless you revoke access)!\n");
+
+        Ok(())
+    }
+
+    /// Open the browser and catch the redirect on the local callback listener
+    async fn receive_code_via_callback(
+        authorize_url: &str,
+        redirect_uri: &str,
+        request: &mut AuthorizationRequest,
+    ) -> Result<String> {
+        println!("\n🔐 Spotify Authentication Required (One-time setup)");
+        println!("1. Your browser will open to Spotify's login page");
+        println!("2. Log in and authorize the application");
+        println!("3. The browser returns to {} and you can close the tab\n", redirect_uri);
+
+        // Open browser automatically
+        if let Err(e) = webbrowser::open(authorize_url) {
+            warn!("Failed to open browser automatically: {}", e);
+            println!("Please manually open this URL: {}", authorize_url);
+        }
+
+        oauth_callback::wait_for_callback(redirect_uri, request, oauth_callback::CALLBACK_TIMEOUT).await
+    }
+
+    /// Fallback for non-local redirect URIs: have the user paste the redirect URL
+    fn receive_code_via_paste(authorize_url: &str, request: &mut AuthorizationRequest) -> Result<String> {
+        println!("\n🔐 Spotify Authentication Required (One-time setup)");
+        println!("1. Your browser will open to Spotify's login page");
+        println!("2. Log in and authorize the application");
+        println!("3. You'll be redirected to a page that won't load - that's normal!");
+        println!("4. Copy the ENTIRE URL from your browser's address bar");
+        println!("5. Paste it here when prompted\n");
+
+        // Open browser automatically
+        if let Err(e) = webbrowser::open(authorize_url) {
+            warn!("Failed to open browser automatically: {}", e);
+            println!("Please manually open this URL: {}", authorize_url);
+        }
+
+        // Get redirect URL from user
+        println!("Paste the redirect URL here:");
+        let mut input = String::new();
+        std::io::stdin().read_line(&mut input)?;
+        let redirect_url = input.trim();
+
+        // Parse authorization code from the URL
+        let parsed_url = Url::parse(redirect_url)
+            .context("Invalid URL. Please make sure you copied the complete URL from your browser.")?;
+
+        Ok(request.accept(&oauth_callback::parse_callback_params(&parsed_url))?)
+    }
+}
+
+impl SpotifyApi for AuthClient {
+    async fn current_playing(&self) -> ClientResult<Option<CurrentlyPlayingContext>> {
+        // Ask for episodes too, otherwise a playing podcast comes back without an item
+        let additional_types = [AdditionalType::Track, AdditionalType::Episode];
+        with_client!(&self.flow, client => client.current_playing(None, Some(&additional_types)).await)
+    }
+
+    async fn saved_tracks_add(&self, track_ids: &[TrackId<'_>]) -> ClientResult<()> {
+        with_client!(&self.flow, client => client.current_user_saved_tracks_add(track_ids.to_vec()).await)
+    }
+
+    async fn saved_tracks_delete(&self, track_ids: &[TrackId<'_>]) -> ClientResult<()> {
+        with_client!(&self.flow, client => client.current_user_saved_tracks_delete(track_ids.to_vec()).await)
+    }
+
+    async fn saved_tracks_contains(&self, track_ids: &[TrackId<'_>]) -> ClientResult<Vec<bool>> {
+        with_client!(&self.flow, client => client.current_user_saved_tracks_contains(track_ids.to_vec()).await)
+    }
+
+    async fn saved_tracks(&self, limit: u32, offset: u32) -> ClientResult<Page<SavedTrack>> {
+        with_client!(&self.flow, client => client.current_user_saved_tracks_manual(None, Some(limit), Some(offset)).await)
+    }
+
+    async fn current_playback(&self) -> ClientResult<Option<CurrentPlaybackContext>> {
+        with_client!(&self.flow, client => client.current_playback(None, None::<Vec<_>>).await)
+    }
+
+    async fn set_volume(&self, volume_percent: u8) -> ClientResult<()> {
+        with_client!(&self.flow, client => client.volume(volume_percent, None).await)
+    }
+
+    async fn pause_playback(&self) -> ClientResult<()> {
+        with_client!(&self.flow, client => client.pause_playback(None).await)
+    }
+
+    async fn resume_playback(&self) -> ClientResult<()> {
+        with_client!(&self.flow, client => client.resume_playback(None, None).await)
+    }
+
+    async fn next_track(&self) -> ClientResult<()> {
+        with_client!(&self.flow, client => client.next_track(None).await)
+    }
+
+    async fn previous_track(&self) -> ClientResult<()> {
+        with_client!(&self.flow, client => client.previous_track(None).await)
+    }
+
+    async fn seek_track(&self, position_ms: u32) -> ClientResult<()> {
+        let position = chrono::Duration::milliseconds(i64::from(position_ms));
+        with_client!(&self.flow, client => client.seek_track(position, None).await)
+    }
+
+    async fn set_shuffle(&self, shuffle: bool) -> ClientResult<()> {
+        with_client!(&self.flow, client => client.shuffle(shuffle, None).await)
+    }
+
+    async fn set_repeat(&self, repeat: RepeatState) -> ClientResult<()> {
+        with_client!(&self.flow, client => client.repeat(repeat, None).await)
+    }
+
+    async fn devices(&self) -> ClientResult<Vec<Device>> {
+        with_client!(&self.flow, client => client.device().await)
+    }
+
+    async fn transfer_playback(&self, device_id: &str) -> ClientResult<()> {
+        with_client!(&self.flow, client => client.transfer_playback(device_id, None).await)
+    }
+
+    async fn current_user(&self) -> ClientResult<PrivateUser> {
+        with_client!(&self.flow, client => client.current_user().await)
+    }
+
+    /// Refresh the access token through the flow's own refresh path
+    async fn refresh_token(&self) -> ClientResult<()> {
+        with_client!(&self.flow, client => client.refresh_token().await)
+    }
+
+    async fn token(&self) -> Option<Token> {
+        self.get_token().lock().await.unwrap().clone()
+    }
+
+    fn reauthorize(&self, scopes: HashSet<String>) -> Step<'static, Token> {
+        let mut client = self.detached();
+        Box::pin(async move {
+            let mut requested = client.get_oauth().scopes.clone();
+            requested.extend(scopes);
+            client.set_scopes(requested);
+
+            client.authorize_interactively().await?;
+            client.token().await
+                .ok_or_else(|| anyhow!("Authorization completed but no token was set in client"))
+        })
+    }
+
+    async fn set_token(&self, token: Token) {
+        *self.get_token().lock().await.unwrap() = Some(token);
+    }
+}
@@ -1,24 +1,141 @@
 use anyhow::{Context, Result};
+use global_hotkey::hotkey::HotKey;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
-use std::{fs, io::{self, Write}, path::PathBuf};
+use std::{collections::BTreeMap, fmt, fs, io::{self, Write}, path::PathBuf, str::FromStr, time::Duration};
+use url::Url;
+
+/// Name of the profile backed by the `[spotify]` section
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Longest wait before a verification read, whatever the backoff strategy
+const MAX_VERIFICATION_DELAY_MS: u64 = 30_000;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
+    /// Profile used at startup, remembered when switching accounts from the tray
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_profile: Option<String>,
     pub spotify: SpotifyConfig,
+    #[serde(default)]
     pub hotkeys: HotkeyConfig,
     pub notifications: NotificationConfig,
+    #[serde(default)]
+    pub token_storage: TokenStorageConfig,
+    #[serde(default)]
+    pub verification: VerificationConfig,
+    #[serde(default)]
+    pub playback: PlaybackConfig,
+    /// Additional named accounts, each with its own credentials and token cache
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, SpotifyConfig>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SpotifyConfig {
     pub client_id: String,
+    /// Only used by the "code" flow; left out of config.toml when empty
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub client_secret: String,
     pub redirect_uri: String,
+    #[serde(default)]
+    pub auth_flow: AuthFlow,
+    /// Web API base URL, for a local stand-in or an egress proxy; api.spotify.com when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_base_url: Option<String>,
+    /// Accounts service base URL (authorize and token endpoints); accounts.spotify.com when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accounts_base_url: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// OAuth authorization flow used to obtain tokens
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthFlow {
+    /// Authorization code flow with client secret
+    #[default]
+    Code,
+    /// Authorization code flow with PKCE, no client secret needed
+    Pkce,
+}
+
+impl fmt::Display for AuthFlow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthFlow::Code => write!(f, "authorization code"),
+            AuthFlow::Pkce => write!(f, "PKCE"),
+        }
+    }
+}
+
+/// Global hotkeys such as "Ctrl+Alt+L", an empty string turns a hotkey off
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct HotkeyConfig {
     pub like_track: String,
+    pub unlike_track: String,
+    pub toggle_like: String,
+    pub play_pause: String,
+    pub next_track: String,
+    pub previous_track: String,
+    pub volume_up: String,
+    pub volume_down: String,
+    pub mute: String,
+    pub seek_forward: String,
+    pub seek_backward: String,
+    pub restart_track: String,
+    pub toggle_shuffle: String,
+    pub cycle_repeat: String,
+    /// Extra seek hotkeys, each with its own jump, on top of `seek_forward` / `seek_backward`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub seek: Vec<SeekHotkey>,
+}
+
+/// A seek hotkey with its own jump, e.g. a minute ahead for audiobooks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeekHotkey {
+    pub hotkey: String,
+    /// Seconds to jump ahead, negative to jump back
+    pub seconds: i32,
+}
+
+/// Settings of the playback controls
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlaybackConfig {
+    /// Percentage points per volume up/down press
+    pub volume_step: u8,
+    /// Seconds the seek_forward hotkey jumps ahead
+    pub seek_forward_seconds: u32,
+    /// Seconds the seek_backward hotkey jumps back
+    pub seek_backward_seconds: u32,
+    /// Device name or ID playback moves to at startup when nothing is playing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preferred_device: Option<String>,
+}
+
+impl Default for PlaybackConfig {
+    fn default() -> Self {
+        Self {
+            volume_step: 10,
+            seek_forward_seconds: 15,
+            seek_backward_seconds: 15,
+            preferred_device: None,
+        }
+    }
+}
+
+impl PlaybackConfig {
+    /// Reject settings that can't work, with the reason
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if !(1..=100).contains(&self.volume_step) {
+            return Err("volume_step must be between 1 and 100".to_string());
+        }
+        if self.seek_forward_seconds == 0 || self.seek_backward_seconds == 0 {
+            return Err("seek_forward_seconds and seek_backward_seconds must be at least 1".to_string());
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,21 +144,225 @@ pub struct NotificationConfig {
     pub timeout_ms: u32,
 }
 
+/// Where the OAuth token (including the refresh token) is persisted
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TokenStorageConfig {
+    #[serde(default)]
+    pub backend: TokenBackend,
+    /// Only used by the "encrypted" backend
+    #[serde(default)]
+    pub encryption_key: EncryptionKeySource,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenBackend {
+    /// spotify_token.json in the cache directory, unencrypted
+    Plaintext,
+    /// spotify_token.enc in the cache directory
+    #[default]
+    Encrypted,
+    /// OS keyring (Secret Service on Linux)
+    Keyring,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EncryptionKeySource {
+    /// Key derived from this machine's ID
+    #[default]
+    Machine,
+    /// Key derived from a passphrase (env var or prompt)
+    Passphrase,
+}
+
+impl fmt::Display for EncryptionKeySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncryptionKeySource::Machine => write!(f, "machine ID"),
+            EncryptionKeySource::Passphrase => write!(f, "passphrase"),
+        }
+    }
+}
+
+/// How likes and unlikes are verified by reading the library back
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VerificationConfig {
+    /// When off, an action is reported as done as soon as Spotify accepts the write
+    pub enabled: bool,
+    /// Wait before the first verification read
+    pub base_delay_ms: u64,
+    pub max_attempts: u32,
+    pub backoff: BackoffStrategy,
+    /// Added per attempt by the linear strategy
+    pub step_ms: u64,
+    /// Repeat the write during the last attempts if it still hasn't shown up
+    pub reissue: bool,
+}
+
+/// How the wait between verification reads grows
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackoffStrategy {
+    /// base_delay_ms, then step_ms more per attempt
+    #[default]
+    Linear,
+    /// base_delay_ms, doubled per attempt
+    Exponential,
+    /// Like exponential, each wait randomly shortened by up to half
+    ExponentialJitter,
+}
+
+impl Default for VerificationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            base_delay_ms: 1000,
+            max_attempts: 8,
+            backoff: BackoffStrategy::Linear,
+            step_ms: 500,
+            reissue: true,
+        }
+    }
+}
+
+impl VerificationConfig {
+    /// Wait before verification attempt `attempt` (counting from 1)
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let steps = attempt.saturating_sub(1);
+        let delay_ms = match self.backoff {
+            BackoffStrategy::Linear => self.base_delay_ms.saturating_add(self.step_ms.saturating_mul(steps as u64)),
+            BackoffStrategy::Exponential | BackoffStrategy::ExponentialJitter => {
+                self.base_delay_ms.saturating_mul(2u64.saturating_pow(steps))
+            }
+        }
+        .min(MAX_VERIFICATION_DELAY_MS);
+        
+        let delay_ms = match self.backoff {
+            BackoffStrategy::ExponentialJitter => rand::thread_rng().gen_range(delay_ms / 2..=delay_ms),
+            _ => delay_ms,
+        };
+        Duration::from_millis(delay_ms)
+    }
+    
+    /// Whether to repeat the write on attempt `attempt`: each of the last three, when enabled
+    pub fn reissue_on(&self, attempt: u32) -> bool {
+        self.reissue && attempt + 2 >= self.max_attempts
+    }
+    
+    /// Near-instant verification for tests
+    #[cfg(test)]
+    pub fn for_tests(max_attempts: u32) -> Self {
+        Self { base_delay_ms: 1, step_ms: 1, max_attempts, ..Default::default() }
+    }
+    
+    /// Reject settings that can't work, with the reason
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.max_attempts == 0 {
+            return Err("max_attempts must be at least 1, or set enabled = false to skip verification".to_string());
+        }
+        if self.reissue && self.max_attempts < 2 {
+            return Err("reissue needs max_attempts of at least 2, so a repeated write is read back again".to_string());
+        }
+        if self.base_delay_ms > MAX_VERIFICATION_DELAY_MS {
+            return Err(format!("base_delay_ms can be at most {}", MAX_VERIFICATION_DELAY_MS));
+        }
+        Ok(())
+    }
+}
+
+impl Default for HotkeyConfig {
+    fn default() -> Self {
+        Self {
+            like_track: "Ctrl+Alt+L".to_string(),
+            unlike_track: "Ctrl+Alt+U".to_string(),
+            toggle_like: String::new(),
+            play_pause: String::new(),
+            next_track: String::new(),
+            previous_track: String::new(),
+            volume_up: String::new(),
+            volume_down: String::new(),
+            mute: String::new(),
+            seek_forward: String::new(),
+            seek_backward: String::new(),
+            restart_track: String::new(),
+            toggle_shuffle: String::new(),
+            cycle_repeat: String::new(),
+            seek: Vec::new(),
+        }
+    }
+}
+
+impl HotkeyConfig {
+    /// Each configured hotkey with its setting name, leaving out the ones turned off
+    pub fn bindings(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("like_track", &self.like_track),
+            ("unlike_track", &self.unlike_track),
+            ("toggle_like", &self.toggle_like),
+            ("play_pause", &self.play_pause),
+            ("next_track", &self.next_track),
+            ("previous_track", &self.previous_track),
+            ("volume_up", &self.volume_up),
+            ("volume_down", &self.volume_down),
+            ("mute", &self.mute),
+            ("seek_forward", &self.seek_forward),
+            ("seek_backward", &self.seek_backward),
+            ("restart_track", &self.restart_track),
+            ("toggle_shuffle", &self.toggle_shuffle),
+            ("cycle_repeat", &self.cycle_repeat),
+        ]
+        .into_iter()
+        .filter(|(_, hotkey)| !hotkey.trim().is_empty())
+        .map(|(name, hotkey)| (name, hotkey.as_str()))
+        .collect()
+    }
+    
+    /// Reject hotkeys that don't parse or are bound twice, with the reason
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if let Some(seek) = self.seek.iter().find(|seek| seek.seconds == 0) {
+            return Err(format!("seek hotkey \"{}\" must jump at least 1 second", seek.hotkey));
+        }
+        
+        let mut seen: Vec<(HotKey, &str)> = Vec::new();
+        let seek_bindings = self.seek.iter().map(|seek| ("seek", seek.hotkey.as_str()));
+        for (name, text) in self.bindings().into_iter().chain(seek_bindings) {
+            let hotkey = HotKey::from_str(text)
+                .map_err(|e| format!("{} = \"{}\" is not a valid hotkey: {}", name, text, e))?;
+            if let Some((_, other)) = seen.iter().find(|(existing, _)| *existing == hotkey) {
+                return Err(format!("{} and {} are both bound to \"{}\"", other, name, text));
+            }
+            seen.push((hotkey, name));
+        }
+        Ok(())
+    }
+}
+
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
+            active_profile: None,
             spotify: SpotifyConfig {
                 client_id: "YOUR_SPOTIFY_CLIENT_ID".to_string(),
                 client_secret: "YOUR_SPOTIFY_CLIENT_SECRET".to_string(),
-                redirect_uri: "https://example.com/callback".to_string(),
-            },
-            hotkeys: HotkeyConfig {
-                like_track: "Ctrl+Alt+L".to_string(),
+                redirect_uri: "http://127.0.0.1:8888/callback".to_string(),
+                auth_flow: AuthFlow::Code,
+                api_base_url: None,
+                accounts_base_url: None,
             },
+            hotkeys: HotkeyConfig::default(),
             notifications: NotificationConfig {
                 enabled: true,
                 timeout_ms: 3000,
             },
+            token_storage: TokenStorageConfig::default(),
+            verification: VerificationConfig::default(),
+            playback: PlaybackConfig::default(),
+            profiles: BTreeMap::new(),
         }
     }
 }
@@ -54,7 +375,7 @@ impl AppConfig {
             let config_str = fs::read_to_string(&config_path)
                 .context("Failed to read config file")?;
             
-            let config: Self = toml::from_str(&config_str)
+            let mut config: Self = toml::from_str(&config_str)
                 .context("Failed to parse config file")?;
             
             // Validate Spotify credentials
@@ -62,11 +383,60 @@ impl AppConfig {
                 eprintln!("⚠️  Please update your Spotify credentials in: {}", config_path.display());
                 eprintln!("   1. Go to https://developer.spotify.com/dashboard");
                 eprintln!("   2. Create a new app");
-                eprintln!("   3. Set redirect URI to: https://example.com/callback");
+                eprintln!("   3. Set redirect URI to: http://127.0.0.1:8888/callback");
                 eprintln!("   4. Copy Client ID and Client Secret to the config file");
                 std::process::exit(1);
             }
             
+            for name in config.profile_names() {
+                if !is_valid_profile_name(&name) || (name == DEFAULT_PROFILE && config.profiles.contains_key(DEFAULT_PROFILE)) {
+                    eprintln!("⚠️  Invalid profile name \"{}\" in: {}", name, config_path.display());
+                    eprintln!("   Use letters, digits, '-' or '_' - \"{}\" is reserved for the [spotify] section", DEFAULT_PROFILE);
+                    std::process::exit(1);
+                }
+                
+                let profile = config.profile(&name).expect("listed profile exists");
+                if profile.auth_flow == AuthFlow::Code && profile.client_secret.is_empty() {
+                    eprintln!("⚠️  No client_secret set for profile \"{}\" in: {}", name, config_path.display());
+                    eprintln!("   Either add your Client Secret, or set auth_flow = \"pkce\" to log in without one");
+                    std::process::exit(1);
+                }
+                
+                for (key, value) in [("api_base_url", &profile.api_base_url), ("accounts_base_url", &profile.accounts_base_url)] {
+                    if let Some(url) = value.as_deref().filter(|url| !is_valid_base_url(url)) {
+                        eprintln!("⚠️  Invalid {} \"{}\" for profile \"{}\" in: {}", key, url, name, config_path.display());
+                        eprintln!("   Use an absolute http:// or https:// URL, or remove the setting to use Spotify's servers");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            
+            if let Err(problem) = config.hotkeys.validate() {
+                eprintln!("⚠️  Invalid [hotkeys] settings in: {}", config_path.display());
+                eprintln!("   {}", problem);
+                eprintln!("   Use a combination like \"Ctrl+Alt+L\", or \"\" to turn a hotkey off");
+                std::process::exit(1);
+            }
+            
+            if let Err(problem) = config.playback.validate() {
+                eprintln!("⚠️  Invalid [playback] settings in: {}", config_path.display());
+                eprintln!("   {}", problem);
+                std::process::exit(1);
+            }
+            
+            if let Err(problem) = config.verification.validate() {
+                eprintln!("⚠️  Invalid [verification] settings in: {}", config_path.display());
+                eprintln!("   {}", problem);
+                std::process::exit(1);
+            }
+            
+            if let Some(active) = config.active_profile.clone() {
+                if config.profile(&active).is_none() {
+                    eprintln!("⚠️  Remembered profile \"{}\" no longer exists, using \"{}\"", active, DEFAULT_PROFILE);
+                    config.active_profile = None;
+                }
+            }
+            
             Ok(config)
         } else {
             eprintln!("📝 No config file found. Let's set up your Spotify credentials.");
@@ -74,26 +444,34 @@ impl AppConfig {
             eprintln!("   Setup instructions:");
             eprintln!("   1. Go to https://developer.spotify.com/dashboard");
             eprintln!("   2. Create a new app");
-            eprintln!("   3. Set redirect URI to: https://example.com/callback");
+            eprintln!("   3. Set redirect URI to: http://127.0.0.1:8888/callback");
             eprintln!("   4. Copy Client ID and Client Secret below");
+            eprintln!("      (leave the secret empty to use PKCE - it then never gets stored)");
             eprintln!("");
             
             let client_id = Self::prompt_for_input("Enter your Spotify Client ID: ")?;
-            let client_secret = Self::prompt_for_input("Enter your Spotify Client Secret: ")?;
+            let client_secret = Self::prompt_for_optional_input("Enter your Spotify Client Secret (empty for PKCE): ")?;
+            let auth_flow = if client_secret.is_empty() { AuthFlow::Pkce } else { AuthFlow::Code };
             
             let config = Self {
+                active_profile: None,
                 spotify: SpotifyConfig {
                     client_id,
                     client_secret,
-                    redirect_uri: "https://example.com/callback".to_string(),
-                },
-                hotkeys: HotkeyConfig {
-                    like_track: "Ctrl+Alt+L".to_string(),
+                    redirect_uri: "http://127.0.0.1:8888/callback".to_string(),
+                    auth_flow,
+                    api_base_url: None,
+                    accounts_base_url: None,
                 },
+                hotkeys: HotkeyConfig::default(),
                 notifications: NotificationConfig {
                     enabled: true,
                     timeout_ms: 3000,
                 },
+                token_storage: TokenStorageConfig::default(),
+                verification: VerificationConfig::default(),
+                playback: PlaybackConfig::default(),
+                profiles: BTreeMap::new(),
             };
             
             config.save()?;
@@ -105,6 +483,32 @@ impl AppConfig {
         }
     }
     
+    /// All profile names, the default profile first
+    pub fn profile_names(&self) -> Vec<String> {
+        std::iter::once(DEFAULT_PROFILE.to_string())
+            .chain(self.profiles.keys().cloned())
+            .collect()
+    }
+    
+    /// Look up a profile's Spotify settings by name
+    pub fn profile(&self, name: &str) -> Option<&SpotifyConfig> {
+        if name == DEFAULT_PROFILE {
+            Some(&self.spotify)
+        } else {
+            self.profiles.get(name)
+        }
+    }
+    
+    /// Name of the profile currently in use
+    pub fn active_profile_name(&self) -> &str {
+        self.active_profile.as_deref().unwrap_or(DEFAULT_PROFILE)
+    }
+    
+    /// Spotify settings of the profile currently in use
+    pub fn active_spotify(&self) -> &SpotifyConfig {
+        self.profile(self.active_profile_name()).unwrap_or(&self.spotify)
+    }
+    
     pub fn save(&self) -> Result<()> {
         let config_path = Self::config_file_path()?;
         
@@ -130,6 +534,15 @@ impl AppConfig {
     }
     
     fn prompt_for_input(prompt: &str) -> Result<String> {
+        let trimmed = Self::prompt_for_optional_input(prompt)?;
+        if trimmed.is_empty() {
+            anyhow::bail!("Input cannot be empty");
+        }
+        
+        Ok(trimmed)
+    }
+    
+    fn prompt_for_optional_input(prompt: &str) -> Result<String> {
         print!("{}", prompt);
         io::stdout().flush().context("Failed to flush stdout")?;
         
@@ -138,11 +551,124 @@ impl AppConfig {
             .read_line(&mut input)
             .context("Failed to read input")?;
         
-        let trimmed = input.trim().to_string();
-        if trimmed.is_empty() {
-            anyhow::bail!("Input cannot be empty");
+        Ok(input.trim().to_string())
+    }
+}
+
+/// Profile names end up in token cache file names, so keep them simple
+fn is_valid_profile_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Base URLs must be absolute http(s) URLs that paths can be appended to
+fn is_valid_base_url(url: &str) -> bool {
+    Url::parse(url)
+        .map(|url| matches!(url.scheme(), "http" | "https") && url.has_host() && !url.cannot_be_a_base())
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn verification(backoff: BackoffStrategy) -> VerificationConfig {
+        VerificationConfig { base_delay_ms: 100, step_ms: 50, backoff, ..Default::default() }
+    }
+
+    #[test]
+    fn linear_backoff_adds_a_step_per_attempt() {
+        let config = verification(BackoffStrategy::Linear);
+
+        assert_eq!(config.delay(1), Duration::from_millis(100));
+        assert_eq!(config.delay(3), Duration::from_millis(200));
+    }
+
+    #[test]
+    fn exponential_backoff_doubles_up_to_the_cap() {
+        let config = verification(BackoffStrategy::Exponential);
+
+        assert_eq!(config.delay(1), Duration::from_millis(100));
+        assert_eq!(config.delay(4), Duration::from_millis(800));
+        assert_eq!(config.delay(40), Duration::from_millis(MAX_VERIFICATION_DELAY_MS));
+    }
+
+    #[test]
+    fn jitter_stays_within_half_of_the_exponential_delay() {
+        let config = verification(BackoffStrategy::ExponentialJitter);
+
+        for _ in 0..20 {
+            let delay = config.delay(3);
+            assert!(delay >= Duration::from_millis(200) && delay <= Duration::from_millis(400));
         }
-        
-        Ok(trimmed)
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn reissue_covers_the_last_attempts_without_underflow() {
+        let config = VerificationConfig { max_attempts: 8, ..Default::default() };
+        let reissued: Vec<u32> = (1..=8).filter(|&attempt| config.reissue_on(attempt)).collect();
+        assert_eq!(reissued, [6, 7, 8]);
+
+        let config = VerificationConfig { max_attempts: 1, reissue: false, ..Default::default() };
+        assert!(!config.reissue_on(1));
+    }
+
+    #[test]
+    fn validation_rejects_unworkable_settings() {
+        assert!(VerificationConfig::default().validate().is_ok());
+        assert!(VerificationConfig { max_attempts: 0, ..Default::default() }.validate().is_err());
+        assert!(VerificationConfig { max_attempts: 1, ..Default::default() }.validate().is_err());
+        assert!(VerificationConfig { max_attempts: 1, reissue: false, ..Default::default() }.validate().is_ok());
+        assert!(VerificationConfig { enabled: false, max_attempts: 0, ..Default::default() }.validate().is_ok());
+    }
+
+    #[test]
+    fn missing_settings_use_defaults() {
+        let config: VerificationConfig = toml::from_str("backoff = \"exponential_jitter\"").unwrap();
+
+        assert_eq!(config.backoff, BackoffStrategy::ExponentialJitter);
+        assert_eq!(config.max_attempts, 8);
+    }
+
+    #[test]
+    fn hotkeys_keep_defaults_for_missing_bindings() {
+        let config: HotkeyConfig = toml::from_str("like_track = \"Ctrl+Shift+L\"\ntoggle_like = \"\"").unwrap();
+
+        assert_eq!(config.unlike_track, "Ctrl+Alt+U");
+        assert_eq!(config.bindings(), [("like_track", "Ctrl+Shift+L"), ("unlike_track", "Ctrl+Alt+U")]);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn hotkey_validation_rejects_bad_and_duplicate_bindings() {
+        let invalid = HotkeyConfig { toggle_like: "Ctrl+Alt+Nope".to_string(), ..Default::default() };
+        assert!(invalid.validate().unwrap_err().contains("toggle_like"));
+
+        let duplicate = HotkeyConfig { toggle_like: "ctrl+alt+l".to_string(), ..Default::default() };
+        assert!(duplicate.validate().unwrap_err().contains("like_track and toggle_like"));
+    }
+
+    #[test]
+    fn seek_hotkeys_carry_their_own_jump() {
+        let config: HotkeyConfig = toml::from_str(
+            "seek_forward = \"Ctrl+Alt+Right\"\n\n[[seek]]\nhotkey = \"Ctrl+Alt+Shift+Right\"\nseconds = 60\n\n[[seek]]\nhotkey = \"Ctrl+Alt+Shift+Left\"\nseconds = -60"
+        ).unwrap();
+
+        assert_eq!(config.seek[1], SeekHotkey { hotkey: "Ctrl+Alt+Shift+Left".to_string(), seconds: -60 });
+        assert!(config.validate().is_ok());
+
+        let taken = SeekHotkey { hotkey: "Ctrl+Alt+Right".to_string(), seconds: 60 };
+        let duplicate = HotkeyConfig { seek: vec![taken], ..config.clone() };
+        assert!(duplicate.validate().unwrap_err().contains("seek_forward and seek"));
+
+        let still = SeekHotkey { hotkey: "Ctrl+Alt+Shift+Right".to_string(), seconds: 0 };
+        assert!(HotkeyConfig { seek: vec![still], ..Default::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn playback_settings_must_be_usable() {
+        assert!(PlaybackConfig::default().validate().is_ok());
+        assert!(PlaybackConfig { volume_step: 0, ..Default::default() }.validate().is_err());
+        assert!(PlaybackConfig { volume_step: 101, ..Default::default() }.validate().is_err());
+        assert!(PlaybackConfig { seek_backward_seconds: 0, ..Default::default() }.validate().is_err());
+    }
+}
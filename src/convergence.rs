@@ -0,0 +1,264 @@
+use anyhow::Result;
+use std::{fmt::{self, Debug}, future::Future, pin::Pin, time::Instant};
+use tokio::{sync::Mutex, time::sleep};
+use tracing::{error, info, warn};
+
+use crate::config::VerificationConfig;
+use crate::spotify_client::TrackInfo;
+
+/// Outcome of verifying a change to `subject`, a track unless said otherwise
+#[derive(Debug, Clone)]
+pub struct VerificationResult<S = TrackInfo> {
+    pub success: bool,
+    pub subject: S,
+    pub verified_after_ms: u64,
+    /// Reads made, including the one that saw the change
+    pub attempts: u32,
+}
+
+impl<S> VerificationResult<S> {
+    /// Verification is turned off: the write was accepted, that's all we know
+    pub fn unverified(subject: S) -> Self {
+        Self { success: true, subject, verified_after_ms: 0, attempts: 0 }
+    }
+}
+
+/// Probe error ending a verification without a verdict, e.g. the change was overtaken by a newer one
+#[derive(Debug)]
+pub struct Superseded;
+
+impl fmt::Display for Superseded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "change was superseded before it could be verified")
+    }
+}
+
+impl std::error::Error for Superseded {}
+
+/// A probe or re-apply call, borrowing the context it runs against
+pub type Step<'c, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'c>>;
+
+/// Pin down a closure's signature as a `Step`, e.g. to pass it as `Some(step(|context| ...))`
+pub fn step<C, T, F>(f: F) -> F
+where
+    F: for<'c> FnMut(&'c mut C) -> Step<'c, T>,
+{
+    f
+}
+
+/// Poll `probe` until it reports `expected`, following the configured retry policy
+///
+/// The first probe runs right away (attempt 0), so a change that is already
+/// visible verifies without waiting, after a single read. On the last attempts (see
+/// `VerificationConfig::reissue_on`) the mutation is repeated through `reapply`,
+/// if one is given. `context` is locked for each probe (and the re-apply right
+/// after it), never while waiting. Probe errors are logged and count as a missed
+/// attempt, except `Superseded`, which stops verification and returns `None`.
+pub async fn converge<C, T, S, P, R>(
+    context: &Mutex<C>,
+    verification: &VerificationConfig,
+    operation: &str,
+    expected: T,
+    subject: S,
+    mut probe: P,
+    mut reapply: Option<R>,
+) -> Option<VerificationResult<S>>
+where
+    T: PartialEq + Debug,
+    P: for<'c> FnMut(&'c mut C) -> Step<'c, T>,
+    R: for<'c> FnMut(&'c mut C) -> Step<'c, ()>,
+{
+    if !verification.enabled {
+        return Some(VerificationResult::unverified(subject));
+    }
+
+    let start_time = Instant::now();
+    info!("🔍 Starting verification for {} operation", operation);
+
+    for attempt in 0..=verification.max_attempts {
+        if attempt > 0 {
+            let delay = verification.delay(attempt);
+            info!("⏳ Verification attempt {}/{} - waiting {}ms...", attempt, verification.max_attempts, delay.as_millis());
+            sleep(delay).await;
+        }
+
+        let mut context = context.lock().await;
+        match probe(&mut context).await {
+            Ok(state) if state == expected => {
+                let elapsed_ms = start_time.elapsed().as_millis() as u64;
+                info!("✅ {} verified successfully after {}ms and {} reads", operation, elapsed_ms, attempt + 1);
+                return Some(VerificationResult {
+                    success: true,
+                    subject,
+                    verified_after_ms: elapsed_ms,
+                    attempts: attempt + 1,
+                });
+            }
+            Ok(state) => {
+                warn!("❌ Attempt {}/{}: {} not visible yet ({:?}), retrying...", attempt, verification.max_attempts, operation, state);
+
+                if attempt == 0 || !verification.reissue_on(attempt) {
+                    continue;
+                }
+                if let Some(reapply) = reapply.as_mut() {
+                    warn!("🔄 Re-attempting {} operation on attempt {}", operation, attempt);
+                    match reapply(&mut context).await {
+                        Ok(()) => info!("🔄 Re-{} operation completed", operation),
+                        Err(e) => warn!("⚠️ Re-{} attempt failed: {}", operation, e),
+                    }
+                }
+            }
+            Err(e) if e.is::<Superseded>() => {
+                info!("⏭️ {} superseded, stopping verification", operation);
+                return None;
+            }
+            Err(e) => {
+                warn!("⚠️ Attempt {}/{}: Verification API call failed: {}", attempt, verification.max_attempts, e);
+            }
+        }
+    }
+
+    let elapsed_ms = start_time.elapsed().as_millis() as u64;
+    error!("❌ {} verification failed after {} reads and {}ms", operation, verification.max_attempts + 1, elapsed_ms);
+    Some(VerificationResult {
+        success: false,
+        subject,
+        verified_after_ms: elapsed_ms,
+        attempts: verification.max_attempts + 1,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    /// Remote state that shows a write only after `lag` reads
+    #[derive(Default)]
+    struct Remote {
+        value: u32,
+        pending: Option<u32>,
+        lag: u32,
+        reads: u32,
+        reapplies: u32,
+        failing_reads: u32,
+    }
+
+    impl Remote {
+        fn write(&mut self, value: u32, lag: u32) {
+            self.pending = Some(value);
+            self.lag = lag;
+        }
+
+        fn read(&mut self) -> Result<u32> {
+            self.reads += 1;
+            if self.failing_reads > 0 {
+                self.failing_reads -= 1;
+                return Err(anyhow!("read failed"));
+            }
+            if self.lag > 0 {
+                self.lag -= 1;
+            } else if let Some(value) = self.pending.take() {
+                self.value = value;
+            }
+            Ok(self.value)
+        }
+    }
+
+    fn probe(remote: &mut Remote) -> Step<'_, u32> {
+        Box::pin(async move { remote.read() })
+    }
+
+    fn reapply(remote: &mut Remote) -> Step<'_, ()> {
+        Box::pin(async move {
+            remote.reapplies += 1;
+            remote.write(7, 0);
+            Ok(())
+        })
+    }
+
+    const NO_REAPPLY: Option<fn(&mut Remote) -> Step<'_, ()>> = None;
+
+    #[tokio::test]
+    async fn state_already_reached_needs_a_single_read() {
+        let remote = Mutex::new(Remote { value: 7, ..Default::default() });
+
+        let result = converge(&remote, &VerificationConfig::for_tests(3), "SET", 7, "song", probe, NO_REAPPLY).await.unwrap();
+
+        assert!(result.success);
+        assert_eq!(result.attempts, 1);
+        assert_eq!(remote.lock().await.reads, 1);
+    }
+
+    #[tokio::test]
+    async fn lagging_state_is_polled_until_it_converges() {
+        let remote = Mutex::new(Remote::default());
+        remote.lock().await.write(7, 2);
+
+        let result = converge(&remote, &VerificationConfig::for_tests(5), "SET", 7, "song", probe, Some(reapply)).await.unwrap();
+
+        assert!(result.success);
+        assert_eq!(result.attempts, 3);
+        assert_eq!(remote.lock().await.reapplies, 0);
+    }
+
+    #[tokio::test]
+    async fn lost_write_is_reapplied_on_the_last_attempts() {
+        let remote = Mutex::new(Remote::default());
+
+        let result = converge(&remote, &VerificationConfig::for_tests(5), "SET", 7, "song", probe, Some(reapply)).await.unwrap();
+
+        assert!(result.success);
+        assert_eq!(result.attempts, 5);
+        assert_eq!(remote.lock().await.reapplies, 1);
+    }
+
+    #[tokio::test]
+    async fn failing_probes_count_as_missed_attempts() {
+        let remote = Mutex::new(Remote { failing_reads: 2, value: 7, ..Default::default() });
+
+        let result = converge(&remote, &VerificationConfig::for_tests(3), "SET", 7, "song", probe, NO_REAPPLY).await.unwrap();
+
+        assert!(result.success);
+        assert_eq!(result.attempts, 3);
+    }
+
+    #[tokio::test]
+    async fn never_converging_state_fails_after_all_attempts() {
+        let remote = Mutex::new(Remote::default());
+
+        let result = converge(&remote, &VerificationConfig::for_tests(3), "SET", 7, "song", probe, NO_REAPPLY).await.unwrap();
+
+        assert!(!result.success);
+        assert_eq!(result.attempts, 4);
+        assert_eq!(remote.lock().await.reads, 4);
+    }
+
+    #[tokio::test]
+    async fn disabled_verification_never_probes() {
+        let remote = Mutex::new(Remote::default());
+        let verification = VerificationConfig { enabled: false, ..VerificationConfig::for_tests(3) };
+
+        let result = converge(&remote, &verification, "SET", 7, "song", probe, NO_REAPPLY).await.unwrap();
+
+        assert!(result.success);
+        assert_eq!(remote.lock().await.reads, 0);
+    }
+
+    #[tokio::test]
+    async fn superseded_probe_stops_without_a_verdict() {
+        let remote = Mutex::new(Remote::default());
+        let superseded = step(|remote: &mut Remote| -> Step<'_, u32> {
+            Box::pin(async move {
+                remote.reads += 1;
+                Err(Superseded.into())
+            })
+        });
+
+        let result = converge(&remote, &VerificationConfig::for_tests(3), "SET", 7, "song", superseded, Some(reapply)).await;
+
+        assert!(result.is_none());
+        assert_eq!(remote.lock().await.reads, 1);
+        assert_eq!(remote.lock().await.reapplies, 0);
+    }
+}
@@ -0,0 +1,481 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use rspotify::{model::SavedTrack, prelude::*};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use std::{
+    collections::HashSet,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::Mutex as AsyncMutex;
+use tracing::info;
+
+use crate::spotify_api::SpotifyApi;
+use crate::spotify_client::SpotifyManager;
+use crate::token_store;
+
+/// How often the background task pulls new likes from Spotify
+pub const SYNC_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// How often a sync walks the whole library to catch tracks unliked in other clients
+const RECONCILE_INTERVAL: ChronoDuration = ChronoDuration::hours(1);
+
+/// Largest page the saved-tracks endpoint hands out
+pub const PAGE_SIZE: u32 = 50;
+
+const LIBRARY_FILE_STEM: &str = "library";
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS saved_tracks (
+        track_id    TEXT PRIMARY KEY,
+        name        TEXT NOT NULL,
+        artists     TEXT NOT NULL,
+        album       TEXT NOT NULL,
+        duration_ms INTEGER NOT NULL,
+        added_at    INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS saved_tracks_added_at ON saved_tracks (added_at);
+    CREATE TABLE IF NOT EXISTS sync_state (
+        key   TEXT PRIMARY KEY,
+        value INTEGER NOT NULL
+    );
+";
+
+/// Newest `added_at` seen from Spotify, incremental syncs stop once they reach it
+const NEWEST_ADDED_AT: &str = "newest_added_at";
+/// When the library was last fully walked, also marks the initial import as done
+const LAST_RECONCILED_AT: &str = "last_reconciled_at";
+
+/// A track in the local Liked Songs mirror
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LibraryTrack {
+    /// Bare track ID, not the `spotify:track:` URI
+    pub id: String,
+    pub name: String,
+    /// All artists, comma separated
+    pub artists: String,
+    pub album: String,
+    pub duration_ms: u32,
+    pub added_at: DateTime<Utc>,
+}
+
+impl LibraryTrack {
+    /// `None` for local files and other saved items without a track ID
+    pub fn from_saved(saved: &SavedTrack) -> Option<Self> {
+        let track = &saved.track;
+        Some(Self {
+            id: track.id.as_ref()?.id().to_string(),
+            name: track.name.clone(),
+            artists: track.artists.iter().map(|artist| artist.name.as_str()).collect::<Vec<_>>().join(", "),
+            album: track.album.name.clone(),
+            duration_ms: track.duration.num_milliseconds().max(0) as u32,
+            added_at: saved.added_at,
+        })
+    }
+
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            artists: row.get(2)?,
+            album: row.get(3)?,
+            duration_ms: row.get(4)?,
+            added_at: DateTime::from_timestamp(row.get(5)?, 0).unwrap_or_default(),
+        })
+    }
+}
+
+/// Totals over the whole mirror
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LibraryStats {
+    pub tracks: u64,
+    pub total_duration_ms: u64,
+    pub oldest_added_at: Option<DateTime<Utc>>,
+    pub newest_added_at: Option<DateTime<Utc>>,
+}
+
+/// What a sync changed in the mirror
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    pub added: usize,
+    pub removed: usize,
+    /// Whether the whole library was walked (initial import or reconciliation)
+    pub full: bool,
+}
+
+/// Local SQLite mirror of the user's Liked Songs, one database per profile
+pub struct Library {
+    conn: Mutex<Connection>,
+}
+
+impl Library {
+    /// Open (or create) the library database of a profile in the cache directory
+    pub fn open(profile: &str) -> Result<Self> {
+        let path = token_store::cache_dir()?
+            .join(format!("{}.sqlite3", token_store::profile_key(LIBRARY_FILE_STEM, profile)));
+        Self::open_at(&path)
+    }
+
+    pub fn open_at(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open library database at {}", path.display()))?;
+        Self::with_connection(conn)
+    }
+
+    /// Throwaway library for tests
+    #[cfg(test)]
+    pub fn open_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA)
+            .context("Failed to create library schema")?;
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    /// Whether the initial import has completed, before that the mirror can't answer lookups
+    pub fn is_imported(&self) -> Result<bool> {
+        Ok(self.sync_state(LAST_RECONCILED_AT)?.is_some())
+    }
+
+    /// Liked status from the mirror, `None` until the initial import has completed
+    pub fn is_liked(&self, track_id: &str) -> Result<Option<bool>> {
+        if !self.is_imported()? {
+            return Ok(None);
+        }
+
+        let conn = self.conn.lock().unwrap();
+        let found = conn
+            .query_row("SELECT 1 FROM saved_tracks WHERE track_id = ?1", [track_id], |_| Ok(()))
+            .optional()?;
+        Ok(Some(found.is_some()))
+    }
+
+    /// Tracks whose name, artists or album contain `query` (case-insensitive), newest first
+    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<LibraryTrack>> {
+        let escaped = query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+        let pattern = format!("%{}%", escaped);
+
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare(
+            "SELECT track_id, name, artists, album, duration_ms, added_at FROM saved_tracks
+             WHERE name LIKE ?1 ESCAPE '\\' OR artists LIKE ?1 ESCAPE '\\' OR album LIKE ?1 ESCAPE '\\'
+             ORDER BY added_at DESC, track_id LIMIT ?2",
+        )?;
+        let tracks = statement
+            .query_map(params![pattern, limit as i64], LibraryTrack::from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(tracks)
+    }
+
+    /// The most recently liked tracks
+    pub fn recent(&self, limit: usize) -> Result<Vec<LibraryTrack>> {
+        self.query_tracks(Some(limit))
+    }
+
+    /// Every track, newest first, e.g. for exports
+    pub fn tracks(&self) -> Result<Vec<LibraryTrack>> {
+        self.query_tracks(None)
+    }
+
+    fn query_tracks(&self, limit: Option<usize>) -> Result<Vec<LibraryTrack>> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare(
+            "SELECT track_id, name, artists, album, duration_ms, added_at FROM saved_tracks
+             ORDER BY added_at DESC, track_id LIMIT ?1",
+        )?;
+        // A negative LIMIT means no limit in SQLite
+        let limit = limit.map(|limit| limit as i64).unwrap_or(-1);
+        let tracks = statement
+            .query_map([limit], LibraryTrack::from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(tracks)
+    }
+
+    pub fn count(&self) -> Result<u64> {
+        let conn = self.conn.lock().unwrap();
+        Ok(conn.query_row("SELECT COUNT(*) FROM saved_tracks", [], |row| row.get(0))?)
+    }
+
+    pub fn stats(&self) -> Result<LibraryStats> {
+        let conn = self.conn.lock().unwrap();
+        let (tracks, total_duration_ms, oldest, newest): (u64, u64, Option<i64>, Option<i64>) = conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(duration_ms), 0), MIN(added_at), MAX(added_at) FROM saved_tracks",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )?;
+
+        Ok(LibraryStats {
+            tracks,
+            total_duration_ms,
+            oldest_added_at: oldest.and_then(|secs| DateTime::from_timestamp(secs, 0)),
+            newest_added_at: newest.and_then(|secs| DateTime::from_timestamp(secs, 0)),
+        })
+    }
+
+    /// Insert or refresh tracks, returns how many were not in the mirror before
+    pub fn upsert(&self, tracks: &[LibraryTrack]) -> Result<usize> {
+        let mut conn = self.conn.lock().unwrap();
+        let transaction = conn.transaction()?;
+        let mut added = 0;
+        {
+            let mut exists = transaction.prepare("SELECT 1 FROM saved_tracks WHERE track_id = ?1")?;
+            let mut upsert = transaction.prepare(
+                "INSERT INTO saved_tracks (track_id, name, artists, album, duration_ms, added_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT (track_id) DO UPDATE SET
+                     name = excluded.name, artists = excluded.artists, album = excluded.album,
+                     duration_ms = excluded.duration_ms, added_at = excluded.added_at",
+            )?;
+            for track in tracks {
+                if !exists.exists([&track.id])? {
+                    added += 1;
+                }
+                upsert.execute(params![
+                    track.id,
+                    track.name,
+                    track.artists,
+                    track.album,
+                    track.duration_ms,
+                    track.added_at.timestamp(),
+                ])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(added)
+    }
+
+    pub fn remove(&self, track_id: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM saved_tracks WHERE track_id = ?1", [track_id])?;
+        Ok(())
+    }
+
+    /// Drop every track not in `track_ids`, returns how many were removed
+    fn retain_only(&self, track_ids: &HashSet<String>) -> Result<usize> {
+        let mut conn = self.conn.lock().unwrap();
+        let transaction = conn.transaction()?;
+        let stored: Vec<String> = transaction
+            .prepare("SELECT track_id FROM saved_tracks")?
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+
+        let mut removed = 0;
+        for track_id in stored.iter().filter(|id| !track_ids.contains(*id)) {
+            removed += transaction.execute("DELETE FROM saved_tracks WHERE track_id = ?1", [track_id])?;
+        }
+        transaction.commit()?;
+        Ok(removed)
+    }
+
+    fn sync_state(&self, key: &str) -> Result<Option<DateTime<Utc>>> {
+        let conn = self.conn.lock().unwrap();
+        let value: Option<i64> = conn
+            .query_row("SELECT value FROM sync_state WHERE key = ?1", [key], |row| row.get(0))
+            .optional()?;
+        Ok(value.and_then(|secs| DateTime::from_timestamp(secs, 0)))
+    }
+
+    fn set_sync_state(&self, key: &str, value: DateTime<Utc>) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO sync_state (key, value) VALUES (?1, ?2)
+             ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+            params![key, value.timestamp()],
+        )?;
+        Ok(())
+    }
+
+    /// Whether the next sync should walk the whole library
+    fn needs_reconcile(&self) -> Result<bool> {
+        Ok(match self.sync_state(LAST_RECONCILED_AT)? {
+            Some(last) => Utc::now() - last >= RECONCILE_INTERVAL,
+            None => true,
+        })
+    }
+}
+
+/// Bring the manager's library up to date with Spotify
+///
+/// Walks the whole library on the first run and once per reconcile interval,
+/// dropping tracks that were unliked elsewhere; otherwise only pages through
+/// the newest likes until it reaches ones already mirrored. The manager is
+/// locked per page so user actions are not held up by a large import.
+pub async fn sync<A: SpotifyApi>(manager: &AsyncMutex<SpotifyManager<A>>) -> Result<SyncReport> {
+    let Some(library) = manager.lock().await.library() else {
+        return Ok(SyncReport::default());
+    };
+
+    let full = library.needs_reconcile()?;
+    let watermark = library.sync_state(NEWEST_ADDED_AT)?;
+    let mut report = SyncReport { full, ..Default::default() };
+    let mut seen = HashSet::new();
+    let mut newest: Option<DateTime<Utc>> = None;
+    let mut offset = 0;
+
+    loop {
+        let page = {
+            let mut manager = manager.lock().await;
+            // A profile switch brings its own library, don't mix the two
+            if !manager.library().is_some_and(|current| Arc::ptr_eq(&current, &library)) {
+                return Err(anyhow!("Profile changed during library sync"));
+            }
+            manager.saved_tracks_page(offset, PAGE_SIZE).await?
+        };
+
+        let tracks: Vec<LibraryTrack> = page.items.iter().filter_map(LibraryTrack::from_saved).collect();
+        report.added += library.upsert(&tracks)?;
+        seen.extend(tracks.iter().map(|track| track.id.clone()));
+        newest = page.items.iter().map(|saved| saved.added_at).chain(newest).max();
+
+        // Pages are newest first, so anything older than the watermark is already mirrored
+        let caught_up = !full && watermark.is_some_and(|watermark| {
+            page.items.iter().any(|saved| saved.added_at < watermark)
+        });
+        if caught_up || page.next.is_none() || page.items.is_empty() {
+            break;
+        }
+        offset += page.items.len() as u32;
+    }
+
+    if full {
+        report.removed = library.retain_only(&seen)?;
+        library.set_sync_state(LAST_RECONCILED_AT, Utc::now())?;
+    }
+    if let Some(newest) = newest.filter(|newest| watermark.is_none_or(|watermark| *newest > watermark)) {
+        library.set_sync_state(NEWEST_ADDED_AT, newest)?;
+    }
+
+    if report.added > 0 || report.removed > 0 {
+        info!("📚 Library sync: {} added, {} removed ({} tracks)", report.added, report.removed, library.count()?);
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VerificationConfig;
+    use crate::spotify_api::fake::FakeSpotifyApi;
+
+    fn track(id: &str, name: &str, artists: &str, added_at: i64) -> LibraryTrack {
+        LibraryTrack {
+            id: id.to_string(),
+            name: name.to_string(),
+            artists: artists.to_string(),
+            album: "Album".to_string(),
+            duration_ms: 1000,
+            added_at: DateTime::from_timestamp(added_at, 0).unwrap(),
+        }
+    }
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    fn manager(api: FakeSpotifyApi) -> (AsyncMutex<SpotifyManager<FakeSpotifyApi>>, Arc<Library>) {
+        let library = Arc::new(Library::open_in_memory().unwrap());
+        let mut manager = SpotifyManager::with_api(api, VerificationConfig::for_tests(3));
+        manager.attach_library(Arc::clone(&library));
+        (AsyncMutex::new(manager), library)
+    }
+
+    #[test]
+    fn queries_search_and_order_tracks() {
+        let library = Library::open_in_memory().unwrap();
+        library.upsert(&[
+            track("a", "Bohemian Rhapsody", "Queen", 1),
+            track("b", "Under Pressure", "Queen, David Bowie", 2),
+            track("c", "100% Pure Love", "Crystal Waters", 3),
+        ]).unwrap();
+
+        let ids = |tracks: Vec<LibraryTrack>| tracks.into_iter().map(|t| t.id).collect::<Vec<_>>();
+        assert_eq!(ids(library.search("queen", 10).unwrap()), ["b", "a"]);
+        assert_eq!(ids(library.search("bowie", 10).unwrap()), ["b"]);
+        assert_eq!(ids(library.search("%", 10).unwrap()), ["c"]);
+        assert_eq!(ids(library.recent(2).unwrap()), ["c", "b"]);
+        assert_eq!(library.tracks().unwrap().len(), 3);
+
+        let stats = library.stats().unwrap();
+        assert_eq!(stats.tracks, 3);
+        assert_eq!(stats.total_duration_ms, 3000);
+        assert_eq!(stats.oldest_added_at, DateTime::from_timestamp(1, 0));
+    }
+
+    #[test]
+    fn lookups_wait_for_initial_import() {
+        let library = Library::open_in_memory().unwrap();
+        library.upsert(&[track("a", "Song", "Artist", 1)]).unwrap();
+
+        assert_eq!(library.is_liked("a").unwrap(), None);
+
+        library.set_sync_state(LAST_RECONCILED_AT, Utc::now()).unwrap();
+        assert_eq!(library.is_liked("a").unwrap(), Some(true));
+        assert_eq!(library.is_liked("b").unwrap(), Some(false));
+    }
+
+    #[tokio::test]
+    async fn initial_sync_imports_every_page() {
+        let api = FakeSpotifyApi::new();
+        for i in 0..120 {
+            api.like_at(&format!("track{}", i), at(i));
+        }
+        let (manager, library) = manager(api);
+
+        let report = sync(&manager).await.unwrap();
+
+        assert_eq!(report, SyncReport { added: 120, removed: 0, full: true });
+        assert_eq!(library.count().unwrap(), 120);
+        assert_eq!(library.recent(1).unwrap()[0].id, "track119");
+        assert_eq!(library.is_liked("track0").unwrap(), Some(true));
+    }
+
+    #[tokio::test]
+    async fn incremental_sync_stops_at_mirrored_tracks() {
+        let api = FakeSpotifyApi::new();
+        for i in 0..120 {
+            api.like_at(&format!("track{}", i), at(i));
+        }
+        let (manager, library) = manager(api);
+        sync(&manager).await.unwrap();
+
+        manager.lock().await.api().like_at("new", at(500));
+        let calls_before = manager.lock().await.api().saved_tracks_calls();
+        let report = sync(&manager).await.unwrap();
+
+        assert_eq!(report, SyncReport { added: 1, removed: 0, full: false });
+        assert_eq!(manager.lock().await.api().saved_tracks_calls() - calls_before, 1);
+        assert_eq!(library.is_liked("new").unwrap(), Some(true));
+    }
+
+    #[tokio::test]
+    async fn reconciliation_drops_tracks_unliked_elsewhere() {
+        let api = FakeSpotifyApi::new();
+        api.like_at("kept", at(1));
+        api.like_at("gone", at(2));
+        let (manager, library) = manager(api);
+        sync(&manager).await.unwrap();
+
+        manager.lock().await.api().unlike("gone");
+        assert_eq!(sync(&manager).await.unwrap().removed, 0);
+        assert_eq!(library.is_liked("gone").unwrap(), Some(true));
+
+        library.set_sync_state(LAST_RECONCILED_AT, Utc::now() - RECONCILE_INTERVAL).unwrap();
+        let report = sync(&manager).await.unwrap();
+
+        assert_eq!(report, SyncReport { added: 0, removed: 1, full: true });
+        assert_eq!(library.is_liked("gone").unwrap(), Some(false));
+        assert_eq!(library.is_liked("kept").unwrap(), Some(true));
+    }
+
+    #[tokio::test]
+    async fn sync_without_library_does_nothing() {
+        let manager = AsyncMutex::new(SpotifyManager::with_api(FakeSpotifyApi::new(), VerificationConfig::for_tests(3)));
+
+        assert_eq!(sync(&manager).await.unwrap(), SyncReport::default());
+        assert_eq!(manager.lock().await.api().saved_tracks_calls(), 0);
+    }
+}
@@ -0,0 +1,90 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// How long a liked status is trusted before asking Spotify again
+pub const DEFAULT_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Liked status per track ID, as last written or read by this app
+///
+/// Fed by our own add/delete calls and by every `contains` result. Entries
+/// expire after the TTL so changes made in other Spotify clients show up.
+#[derive(Debug)]
+pub struct LikedCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, (bool, Instant)>>,
+}
+
+impl Default for LikedCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_TTL)
+    }
+}
+
+impl LikedCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self { ttl, entries: Mutex::new(HashMap::new()) }
+    }
+
+    /// Cached status, `None` if unknown or expired
+    pub fn get(&self, track_id: &str) -> Option<bool> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(track_id) {
+            Some((liked, stored_at)) if stored_at.elapsed() < self.ttl => Some(*liked),
+            Some(_) => {
+                entries.remove(track_id);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Store a status, dropping expired entries so tracks never looked up again don't pile up
+    pub fn set(&self, track_id: &str, liked: bool) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, (_, stored_at)| stored_at.elapsed() < self.ttl);
+        entries.insert(track_id.to_string(), (liked, Instant::now()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn returns_latest_status() {
+        let cache = LikedCache::default();
+
+        cache.set("track", true);
+        cache.set("track", false);
+
+        assert_eq!(cache.get("track"), Some(false));
+        assert_eq!(cache.get("other"), None);
+    }
+
+    #[test]
+    fn entries_expire_after_ttl() {
+        let cache = LikedCache::new(Duration::from_millis(10));
+
+        cache.set("track", true);
+        std::thread::sleep(Duration::from_millis(20));
+
+        assert_eq!(cache.get("track"), None);
+    }
+
+    #[test]
+    fn expired_entries_are_pruned_on_set() {
+        let cache = LikedCache::new(Duration::from_millis(10));
+
+        cache.set("first", true);
+        cache.set("second", true);
+        std::thread::sleep(Duration::from_millis(20));
+        cache.set("third", false);
+
+        let entries = cache.entries.lock().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert!(entries.contains_key("third"));
+    }
+}
@@ -1,37 +1,77 @@
 use anyhow::{Context, Result};
-use global_hotkey::{hotkey::{Code, HotKey, Modifiers}, GlobalHotKeyEvent, GlobalHotKeyManager};
+use global_hotkey::{hotkey::HotKey, GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
 use notify_rust::Notification;
+use rspotify::model::Device;
 use std::{
+    collections::HashMap,
+    str::FromStr,
     sync::Arc,
     thread,
     time::{Duration, Instant},
 };
 use tokio::sync::{mpsc, Mutex};
 use tray_icon::{
-    menu::{Menu, MenuItem, PredefinedMenuItem, MenuEvent},
+    menu::{CheckMenuItem, Menu, MenuItem, PredefinedMenuItem, MenuEvent, Submenu},
     TrayIconBuilder, TrayIconEvent,
 };
 use tracing::{error, info, warn};
 use winit::event_loop::EventLoop;
 
+mod action_queue;
+mod auth_client;
 mod config;
+mod convergence;
+mod library;
+mod liked_cache;
+#[cfg(test)]
+mod mock_spotify;
+mod oauth_callback;
+mod permissions;
+mod playback;
+mod rate_limit;
+mod spotify_api;
 mod spotify_client;
+mod stats;
+mod token_lifecycle;
+mod token_store;
 
 #[cfg(windows)]
 mod autostart;
 
-use config::AppConfig;
-use spotify_client::SpotifyManager;
+use action_queue::{QueuedAction, ReplayReport};
+use config::{AppConfig, HotkeyConfig};
+use playback::{NoActiveDevice, PlayerModes, VolumeCoalescer};
+use rate_limit::{RateLimited, RateLimiter};
+use spotify_client::{PendingVerification, SpotifyManager};
 
 #[derive(Debug, Clone)]
 pub enum AppMessage {
     LikeCurrentTrack,
     UnlikeCurrentTrack,
+    ToggleLike,
     SaveCurrentTrack,
+    TogglePlayback,
+    NextTrack,
+    PreviousTrack,
+    VolumeUp,
+    VolumeDown,
+    ToggleMute,
+    SeekForward,
+    SeekBackward,
+    SeekBy(i64), // Milliseconds from a [[hotkeys.seek]] binding, negative to go back
+    RestartTrack,
+    ToggleShuffle,
+    CycleRepeat,
+    TransferPlayback(Device), // Move playback to a device picked in the Devices submenu
     ShowCurrentTrack,
     ToggleAutostart,
     ShowHotkeyInfo,
+    ShowStats,
+    SwitchProfile(String), // Switch the active Spotify account profile
+    UpdateActiveProfile(String), // Update the checkmarks in the Account submenu
     UpdateTrayWithTrack(String), // Track info for tray display
+    UpdatePlayerModes(PlayerModes), // Shuffle and repeat state for the Playback submenu
+    UpdateDevices(Vec<Device>), // Rebuild the Devices submenu
     UpdateAutostartStatus(String), // Update autostart menu item text
     UpdateTrayMenu, // Rebuild entire menu with current state
     Quit,
@@ -52,6 +92,13 @@ async fn main() -> Result<()> {
 
     // Load or create config
     let config = AppConfig::load_or_create().context("Failed to load configuration")?;
+    let active_profile = config.active_profile_name().to_string();
+    let profile_names = config.profile_names();
+    let hotkey_config = config.hotkeys.clone();
+    let volume_step = i32::from(config.playback.volume_step);
+    let seek_forward_ms = i64::from(config.playback.seek_forward_seconds) * 1000;
+    let seek_backward_ms = i64::from(config.playback.seek_backward_seconds) * 1000;
+    let preferred_device = config.playback.preferred_device.clone();
     
     // Create event loop for system tray (must be on main thread)
     let event_loop = EventLoop::new().context("Failed to create event loop")?;
@@ -59,33 +106,48 @@ async fn main() -> Result<()> {
     // Create message channel
     let (tx, mut rx) = mpsc::unbounded_channel::<AppMessage>();
     
-    // Initialize Spotify client
-    let spotify_manager = Arc::new(Mutex::new(
-        SpotifyManager::new(&config).await.context("Failed to initialize Spotify client")?
-    ));
-    
-    // Setup global hotkeys
-    let hotkey_manager = GlobalHotKeyManager::new().context("Failed to create hotkey manager")?;
-    
-    // Register hotkeys
-    let like_hotkey = HotKey::new(
-        Some(Modifiers::CONTROL | Modifiers::ALT),
-        Code::KeyL,
-    );
-    let unlike_hotkey = HotKey::new(
-        Some(Modifiers::CONTROL | Modifiers::ALT),
-        Code::KeyU,
-    );
-    
+    // Initialize Spotify client, with a notification since autostart has no console to show the error
+    let spotify_manager = match SpotifyManager::new(&config).await {
+        Ok(manager) => Arc::new(Mutex::new(manager)),
+        Err(e) => {
+            let _ = Notification::new()
+                .summary("❌ Spotify Quick Actions couldn't start")
+                .body(&format!("{:#}", e))
+                .timeout(10000)
+                .show();
+            return Err(e.context("Failed to initialize Spotify client"));
+        }
+    };
+    let rate_limiter = spotify_manager.lock().await.rate_limiter();
+    let shared_config = Arc::new(Mutex::new(config));
     
-    hotkey_manager
-        .register(like_hotkey)
-        .context("Failed to register like hotkey (Ctrl+Alt+L)")?;
-    hotkey_manager
-        .register(unlike_hotkey)
-        .context("Failed to register unlike hotkey (Ctrl+Alt+U)")?;
+    // Move playback to the preferred device unless something is playing already
+    if let Some(preferred) = preferred_device {
+        let manager = Arc::clone(&spotify_manager);
+        tokio::spawn(async move {
+            handle_preferred_device(manager, preferred).await;
+        });
+    }
     
-    info!("Registered global hotkeys: Ctrl+Alt+L (like), Ctrl+Alt+U (unlike)");
+    // Register the configured global hotkeys
+    let hotkey_manager = GlobalHotKeyManager::new().context("Failed to create hotkey manager")?;
+    let mut hotkeys = Vec::new();
+    for (name, text) in hotkey_config.bindings() {
+        let Some((message, _)) = hotkey_action(name) else {
+            continue;
+        };
+        // A combination already taken by the desktop shouldn't keep the app from starting
+        match register_hotkey(&hotkey_manager, name, text) {
+            Ok(hotkey) => hotkeys.push((hotkey.id(), message)),
+            Err(e) => warn!("⚠️ Skipping {} hotkey: {:#}", name, e),
+        }
+    }
+    for seek in &hotkey_config.seek {
+        match register_hotkey(&hotkey_manager, "seek", &seek.hotkey) {
+            Ok(hotkey) => hotkeys.push((hotkey.id(), AppMessage::SeekBy(i64::from(seek.seconds) * 1000))),
+            Err(e) => warn!("⚠️ Skipping seek hotkey: {:#}", e),
+        }
+    }
     
     // Create system tray
     let tray_menu = Menu::new();
@@ -93,8 +155,53 @@ async fn main() -> Result<()> {
     let current_track_item = MenuItem::new("No track playing", false, None);
     let save_item = MenuItem::new("💾 Save Current Track", true, None);
     let unlike_item = MenuItem::new("💔 Remove Current Track", true, None);
+    let toggle_like_item = MenuItem::new("💗 Toggle Like", true, None);
     let separator = PredefinedMenuItem::separator();
     
+    // Playback submenu with the transport controls
+    let playback_menu = Submenu::new("🎛️ Playback", true);
+    let play_pause_item = MenuItem::new("⏯️ Play/Pause", true, None);
+    let next_item = MenuItem::new("⏭️ Next Track", true, None);
+    let previous_item = MenuItem::new("⏮️ Previous Track", true, None);
+    let volume_up_item = MenuItem::new("🔊 Volume Up", true, None);
+    let volume_down_item = MenuItem::new("🔉 Volume Down", true, None);
+    let mute_item = MenuItem::new("🔇 Mute/Unmute", true, None);
+    let seek_forward_item = MenuItem::new("⏩ Seek Forward", true, None);
+    let seek_backward_item = MenuItem::new("⏪ Seek Backward", true, None);
+    let restart_item = MenuItem::new("↩️ Restart Track", true, None);
+    let shuffle_item = CheckMenuItem::new("🔀 Shuffle", true, false, None);
+    let repeat_item = MenuItem::new(playback::repeat_label(PlayerModes::default().repeat), true, None);
+    playback_menu.append_items(&[
+        &play_pause_item,
+        &next_item,
+        &previous_item,
+        &seek_forward_item,
+        &seek_backward_item,
+        &restart_item,
+        &PredefinedMenuItem::separator(),
+        &shuffle_item,
+        &repeat_item,
+        &PredefinedMenuItem::separator(),
+        &volume_up_item,
+        &volume_down_item,
+        &mute_item,
+    ])?;
+    
+    // Devices submenu, filled in by the device refresh task
+    let devices_menu = Submenu::new("🔈 Devices", true);
+    let no_devices_item = MenuItem::new("No devices found", false, None);
+    devices_menu.append(&no_devices_item)?;
+    let mut device_items: Vec<(Device, CheckMenuItem)> = Vec::new();
+    
+    // Account submenu with one entry per configured profile
+    let account_menu = Submenu::new("👤 Account", true);
+    let mut profile_items = Vec::new();
+    for name in &profile_names {
+        let item = CheckMenuItem::new(name, true, *name == active_profile, None);
+        account_menu.append(&item)?;
+        profile_items.push((name.clone(), item));
+    }
+    
     // Create autostart item with current status
     #[cfg(windows)]
     let autostart_text = autostart::get_autostart_status_text();
@@ -103,6 +210,7 @@ async fn main() -> Result<()> {
     let autostart_item = MenuItem::new(&autostart_text, true, None);
     
     let info_item = MenuItem::new("ℹ️ Hotkeys & Info", true, None);
+    let stats_item = MenuItem::new("📊 Stats", true, None);
     let quit_item = MenuItem::new("Quit", true, None);
     
     // Capture menu item references for dynamic updates
@@ -111,8 +219,21 @@ async fn main() -> Result<()> {
     let _current_track_item_id = current_track_item.id();
     let save_item_id = save_item.id();
     let unlike_item_id = unlike_item.id();
+    let toggle_like_item_id = toggle_like_item.id();
+    let play_pause_item_id = play_pause_item.id();
+    let next_item_id = next_item.id();
+    let previous_item_id = previous_item.id();
+    let volume_up_item_id = volume_up_item.id();
+    let volume_down_item_id = volume_down_item.id();
+    let mute_item_id = mute_item.id();
+    let seek_forward_item_id = seek_forward_item.id();
+    let seek_backward_item_id = seek_backward_item.id();
+    let restart_item_id = restart_item.id();
+    let shuffle_item_id = shuffle_item.id().clone();
+    let repeat_item_id = repeat_item.id().clone();
     let autostart_item_id = autostart_item.id();
     let info_item_id = info_item.id();
+    let stats_item_id = stats_item.id();
     let quit_item_id = quit_item.id();
     
     tray_menu.append_items(&[
@@ -120,9 +241,14 @@ async fn main() -> Result<()> {
         &separator,
         &save_item,
         &unlike_item,
+        &toggle_like_item,
+        &playback_menu,
+        &devices_menu,
         &separator,
+        &account_menu,
         &autostart_item,
         &info_item,
+        &stats_item,
         &separator,
         &quit_item,
     ])?;
@@ -140,51 +266,96 @@ async fn main() -> Result<()> {
     // Spawn hotkey listener thread
     thread::spawn(move || {
         let global_hotkey_channel = GlobalHotKeyEvent::receiver();
-        let mut last_like_time = Instant::now() - Duration::from_secs(10); // Initialize to allow first trigger
-        let mut last_unlike_time = Instant::now() - Duration::from_secs(10);
-        let mut last_save_time = Instant::now() - Duration::from_secs(10);
+        let mut last_triggered: HashMap<u32, Instant> = HashMap::new();
         let debounce_duration = Duration::from_millis(500); // 500ms debounce
         
         loop {
             if let Ok(event) = global_hotkey_channel.recv() {
-                let now = Instant::now();
+                if event.state != HotKeyState::Pressed {
+                    continue;
+                }
+                let Some((_, message)) = hotkeys.iter().find(|(id, _)| *id == event.id) else {
+                    continue;
+                };
                 
-                if event.id == like_hotkey.id() {
-                    if now.duration_since(last_like_time) >= debounce_duration {
-                        last_like_time = now;
-                        let _ = hotkey_tx.send(AppMessage::LikeCurrentTrack);
-                    }
-                } else if event.id == unlike_hotkey.id() {
-                    if now.duration_since(last_unlike_time) >= debounce_duration {
-                        last_unlike_time = now;
-                        let _ = hotkey_tx.send(AppMessage::UnlikeCurrentTrack);
-                    }
+                // Volume and seek presses are meant to be repeated; volume presses get batched instead
+                if matches!(message, AppMessage::VolumeUp | AppMessage::VolumeDown | AppMessage::SeekForward | AppMessage::SeekBackward | AppMessage::SeekBy(_)) {
+                    let _ = hotkey_tx.send(message.clone());
+                    continue;
+                }
+                
+                let now = Instant::now();
+                if last_triggered.get(&event.id).is_some_and(|last| now.duration_since(*last) < debounce_duration) {
+                    continue;
                 }
+                last_triggered.insert(event.id, now);
+                let _ = hotkey_tx.send(message.clone());
             }
         }
     });
     
     // Clone references for the async task
     let spotify_manager_clone = Arc::clone(&spotify_manager);
+    let poller_rate_limiter = Arc::clone(&rate_limiter);
     let spotify_tx = tx.clone();
     
     // Spawn Spotify management task
     tokio::spawn(async move {
-        let mut interval = tokio::time::interval(Duration::from_secs(2));
+        let mut interval = tokio::time::interval(spotify_client::POLL_INTERVAL);
         let mut last_track_id: Option<String> = None;
+        let mut last_liked: Option<bool> = None;
+        let mut last_modes: Option<PlayerModes> = None;
         
         loop {
             interval.tick().await;
             
+            // Pause while rate limited, and let waiting user actions go first
+            if poller_rate_limiter.is_limited() || poller_rate_limiter.user_actions_pending() {
+                continue;
+            }
+            
             let mut manager = spotify_manager_clone.lock().await;
+            let playback = manager.poll_current_track().await;
+            
+            // Answer from the liked cache or the library, only ask Spotify for new tracks neither knows
+            let current_track = playback.as_ref().ok().and_then(|playback| playback.track.as_ref());
+            let liked = match current_track.and_then(|track| track.id.as_deref()) {
+                Some(track_id) if last_track_id.as_deref() == Some(track_id) => {
+                    manager.cached_track_liked(track_id).or(last_liked)
+                }
+                Some(track_id) => match manager.cached_track_liked(track_id) {
+                    Some(liked) => Some(liked),
+                    None => manager.poll_track_liked(track_id).await,
+                },
+                None => None,
+            };
+            drop(manager);
+            
+            // Keep the shuffle check mark and repeat label in line with what other devices change
+            if let Some(modes) = playback.as_ref().ok().and_then(|playback| playback.modes) {
+                if last_modes != Some(modes) {
+                    last_modes = Some(modes);
+                    let _ = spotify_tx.send(AppMessage::UpdatePlayerModes(modes));
+                }
+            }
             
             // Update current track info
-            if let Ok(current_track) = manager.get_current_track().await {
+            if let Err(e) = &playback {
+                if e.downcast_ref::<RateLimited>().is_some() {
+                    continue;
+                }
+            }
+            if let Some(current_track) = current_track {
                 if let Some(track_id) = &current_track.id {
-                    if Some(track_id.clone()) != last_track_id {
+                    if Some(track_id.clone()) != last_track_id || liked != last_liked {
+                        if Some(track_id.clone()) != last_track_id {
+                            info!("Now playing: {} - {}", current_track.name, current_track.artist);
+                        }
                         last_track_id = Some(track_id.clone());
-                        let track_display = format!("🎵 {} - {}", current_track.name, current_track.artist);
-                        info!("Now playing: {}", track_display);
+                        last_liked = liked;
+                        
+                        let liked_marker = if liked == Some(true) { " ❤️" } else { "" };
+                        let track_display = format!("🎵 {} - {}{}", current_track.name, current_track.artist, liked_marker);
                         
                         // Send message to update tray menu item
                         let _ = spotify_tx.send(AppMessage::UpdateTrayWithTrack(track_display));
@@ -194,14 +365,91 @@ async fn main() -> Result<()> {
                 // No track playing, reset if we had one before
                 if last_track_id.is_some() {
                     last_track_id = None;
+                    last_liked = None;
                     let _ = spotify_tx.send(AppMessage::UpdateTrayWithTrack("No track playing".to_string()));
                 }
             }
         }
     });
     
+    // Keep the local Liked Songs library in sync, starting with the initial import
+    let library_manager = Arc::clone(&spotify_manager);
+    let library_rate_limiter = Arc::clone(&rate_limiter);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(library::SYNC_INTERVAL);
+        
+        loop {
+            interval.tick().await;
+            
+            if library_rate_limiter.is_limited() {
+                continue;
+            }
+            
+            if let Err(e) = library::sync(&library_manager).await {
+                warn!("⚠️ Liked Songs library sync failed: {}", e);
+            }
+        }
+    });
+    
+    // Refresh the Devices submenu as devices come and go or another one takes over
+    let devices_manager = Arc::clone(&spotify_manager);
+    let devices_rate_limiter = Arc::clone(&rate_limiter);
+    let devices_tx = tx.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(10));
+        let mut last_devices: Option<Vec<(Option<String>, String, bool)>> = None;
+        
+        loop {
+            interval.tick().await;
+            
+            if devices_rate_limiter.is_limited() || devices_rate_limiter.user_actions_pending() {
+                continue;
+            }
+            
+            let Ok(devices) = devices_manager.lock().await.poll_devices().await else {
+                continue;
+            };
+            
+            // Volume changes alone don't need a rebuild
+            let shown: Vec<_> = devices.iter()
+                .map(|device| (device.id.clone(), device.name.clone(), device.is_active))
+                .collect();
+            if last_devices.as_ref() != Some(&shown) {
+                last_devices = Some(shown);
+                let _ = devices_tx.send(AppMessage::UpdateDevices(devices));
+            }
+        }
+    });
+    
+    // Replay likes and unlikes queued while Spotify was unreachable
+    let queue_manager = Arc::clone(&spotify_manager);
+    let queue_rate_limiter = Arc::clone(&rate_limiter);
+    tokio::spawn(async move {
+        let mut backoff = action_queue::MIN_BACKOFF;
+        
+        loop {
+            tokio::time::sleep(backoff).await;
+            
+            if queue_rate_limiter.is_limited() {
+                continue;
+            }
+            
+            let report = action_queue::replay(&queue_manager).await;
+            backoff = if report.retry_later {
+                (backoff * 2).min(action_queue::MAX_BACKOFF)
+            } else {
+                action_queue::MIN_BACKOFF
+            };
+            
+            if !report.applied.is_empty() || !report.failed.is_empty() {
+                notify_replay(&report);
+            }
+        }
+    });
+    
     // Handle tray events and messages
     let tray_tx = tx.clone();
+    let volume_coalescer = Arc::new(VolumeCoalescer::default());
     
     event_loop.run(move |_event, elwt| {
         // Handle tray icon events
@@ -220,42 +468,160 @@ async fn main() -> Result<()> {
                 let _ = tray_tx.send(AppMessage::SaveCurrentTrack);
             } else if event.id == unlike_item_id {
                 let _ = tray_tx.send(AppMessage::UnlikeCurrentTrack);
+            } else if event.id == toggle_like_item_id {
+                let _ = tray_tx.send(AppMessage::ToggleLike);
+            } else if event.id == play_pause_item_id {
+                let _ = tray_tx.send(AppMessage::TogglePlayback);
+            } else if event.id == next_item_id {
+                let _ = tray_tx.send(AppMessage::NextTrack);
+            } else if event.id == previous_item_id {
+                let _ = tray_tx.send(AppMessage::PreviousTrack);
+            } else if event.id == volume_up_item_id {
+                let _ = tray_tx.send(AppMessage::VolumeUp);
+            } else if event.id == volume_down_item_id {
+                let _ = tray_tx.send(AppMessage::VolumeDown);
+            } else if event.id == mute_item_id {
+                let _ = tray_tx.send(AppMessage::ToggleMute);
+            } else if event.id == seek_forward_item_id {
+                let _ = tray_tx.send(AppMessage::SeekForward);
+            } else if event.id == seek_backward_item_id {
+                let _ = tray_tx.send(AppMessage::SeekBackward);
+            } else if event.id == restart_item_id {
+                let _ = tray_tx.send(AppMessage::RestartTrack);
+            } else if event.id == shuffle_item_id {
+                // Undo the click's own check toggle, the mark follows Spotify's state once it changed
+                shuffle_item.set_checked(!shuffle_item.is_checked());
+                let _ = tray_tx.send(AppMessage::ToggleShuffle);
+            } else if event.id == repeat_item_id {
+                let _ = tray_tx.send(AppMessage::CycleRepeat);
             } else if event.id == autostart_item_id {
                 let _ = tray_tx.send(AppMessage::ToggleAutostart);
             } else if event.id == info_item_id {
                 let _ = tray_tx.send(AppMessage::ShowHotkeyInfo);
+            } else if event.id == stats_item_id {
+                let _ = tray_tx.send(AppMessage::ShowStats);
             } else if event.id == quit_item_id {
                 let _ = tray_tx.send(AppMessage::Quit);
+            } else if let Some((name, _)) = profile_items.iter().find(|(_, item)| event.id == item.id()) {
+                let _ = tray_tx.send(AppMessage::SwitchProfile(name.clone()));
+            } else if let Some((device, item)) = device_items.iter().find(|(_, item)| event.id == item.id()) {
+                // Undo the click's own check toggle, the mark moves once Spotify reports the new device
+                item.set_checked(!item.is_checked());
+                let _ = tray_tx.send(AppMessage::TransferPlayback(device.clone()));
             }
         }
         
         // Handle application messages
         if let Ok(msg) = rx.try_recv() {
             let spotify_manager = Arc::clone(&spotify_manager);
+            let rate_limiter = Arc::clone(&rate_limiter);
             
             match msg {
                 AppMessage::LikeCurrentTrack => {
                     tokio::spawn(async move {
-                        handle_like_track(spotify_manager).await;
+                        handle_like_track(spotify_manager, rate_limiter).await;
                     });
                 }
                 AppMessage::UnlikeCurrentTrack => {
                     tokio::spawn(async move {
-                        handle_unlike_track(spotify_manager).await;
+                        handle_unlike_track(spotify_manager, rate_limiter).await;
+                    });
+                }
+                AppMessage::ToggleLike => {
+                    tokio::spawn(async move {
+                        handle_toggle_like(spotify_manager, rate_limiter).await;
                     });
                 }
                 AppMessage::SaveCurrentTrack => {
                     tokio::spawn(async move {
-                        handle_save_track(spotify_manager).await;
+                        handle_save_track(spotify_manager, rate_limiter).await;
+                    });
+                }
+                AppMessage::TogglePlayback => {
+                    tokio::spawn(async move {
+                        handle_toggle_playback(spotify_manager, rate_limiter).await;
+                    });
+                }
+                AppMessage::NextTrack => {
+                    tokio::spawn(async move {
+                        handle_skip_track(spotify_manager, rate_limiter, true).await;
+                    });
+                }
+                AppMessage::PreviousTrack => {
+                    tokio::spawn(async move {
+                        handle_skip_track(spotify_manager, rate_limiter, false).await;
+                    });
+                }
+                AppMessage::VolumeUp | AppMessage::VolumeDown => {
+                    let delta = if matches!(msg, AppMessage::VolumeUp) { volume_step } else { -volume_step };
+                    if volume_coalescer.push(delta) {
+                        let coalescer = Arc::clone(&volume_coalescer);
+                        tokio::spawn(async move {
+                            handle_volume_change(spotify_manager, rate_limiter, coalescer).await;
+                        });
+                    }
+                }
+                AppMessage::ToggleMute => {
+                    tokio::spawn(async move {
+                        handle_toggle_mute(spotify_manager, rate_limiter).await;
+                    });
+                }
+                AppMessage::SeekForward => {
+                    tokio::spawn(async move {
+                        handle_seek(spotify_manager, rate_limiter, seek_forward_ms).await;
+                    });
+                }
+                AppMessage::SeekBackward => {
+                    tokio::spawn(async move {
+                        handle_seek(spotify_manager, rate_limiter, -seek_backward_ms).await;
+                    });
+                }
+                AppMessage::SeekBy(offset_ms) => {
+                    tokio::spawn(async move {
+                        handle_seek(spotify_manager, rate_limiter, offset_ms).await;
+                    });
+                }
+                AppMessage::RestartTrack => {
+                    tokio::spawn(async move {
+                        handle_restart_track(spotify_manager, rate_limiter).await;
+                    });
+                }
+                AppMessage::ToggleShuffle => {
+                    let tx_clone = tx.clone();
+                    tokio::spawn(async move {
+                        handle_toggle_shuffle(spotify_manager, rate_limiter, tx_clone).await;
+                    });
+                }
+                AppMessage::CycleRepeat => {
+                    let tx_clone = tx.clone();
+                    tokio::spawn(async move {
+                        handle_cycle_repeat(spotify_manager, rate_limiter, tx_clone).await;
                     });
                 }
                 AppMessage::ShowCurrentTrack => {
                     tokio::spawn(async move {
-                        handle_show_current_track(spotify_manager).await;
+                        handle_show_current_track(spotify_manager, rate_limiter).await;
                     });
                 }
                 AppMessage::ShowHotkeyInfo => {
-                    handle_show_hotkey_info();
+                    handle_show_hotkey_info(&hotkey_config);
+                }
+                AppMessage::ShowStats => {
+                    tokio::spawn(async move {
+                        handle_show_stats(spotify_manager).await;
+                    });
+                }
+                AppMessage::SwitchProfile(profile) => {
+                    let config = Arc::clone(&shared_config);
+                    let tx_clone = tx.clone();
+                    tokio::spawn(async move {
+                        handle_switch_profile(spotify_manager, config, profile, tx_clone).await;
+                    });
+                }
+                AppMessage::UpdateActiveProfile(profile) => {
+                    for (name, item) in &profile_items {
+                        item.set_checked(*name == profile);
+                    }
                 }
                 AppMessage::ToggleAutostart => {
                     let tx_clone = tx.clone();
@@ -267,6 +633,32 @@ async fn main() -> Result<()> {
                     // Update the current track menu item
                     current_track_item_ref.set_text(&track_info);
                 }
+                AppMessage::TransferPlayback(device) => {
+                    let tx_clone = tx.clone();
+                    tokio::spawn(async move {
+                        handle_transfer_playback(spotify_manager, rate_limiter, device, tx_clone).await;
+                    });
+                }
+                AppMessage::UpdateDevices(devices) => {
+                    for (_, item) in device_items.drain(..) {
+                        let _ = devices_menu.remove(&item);
+                    }
+                    let _ = devices_menu.remove(&no_devices_item);
+                    
+                    // Devices without an ID can't be picked through the Web API
+                    for device in devices.into_iter().filter(|device| device.id.is_some()) {
+                        let item = CheckMenuItem::new(&device.name, !device.is_restricted, device.is_active, None);
+                        let _ = devices_menu.append(&item);
+                        device_items.push((device, item));
+                    }
+                    if device_items.is_empty() {
+                        let _ = devices_menu.append(&no_devices_item);
+                    }
+                }
+                AppMessage::UpdatePlayerModes(modes) => {
+                    shuffle_item.set_checked(modes.shuffle);
+                    repeat_item.set_text(playback::repeat_label(modes.repeat));
+                }
                 AppMessage::UpdateAutostartStatus(status_text) => {
                     // Update the autostart menu item
                     autostart_item_ref.set_text(&status_text);
@@ -285,79 +677,346 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
-async fn handle_like_track(spotify_manager: Arc<Mutex<SpotifyManager>>) {
-    let mut manager = spotify_manager.lock().await;
+async fn handle_like_track(spotify_manager: Arc<Mutex<SpotifyManager>>, rate_limiter: Arc<RateLimiter>) {
+    let _priority = rate_limiter.user_action();
+    let result = SpotifyManager::with_consent(&spotify_manager, |manager| Box::pin(manager.like_current_track_optimistic())).await;
     
-    match manager.like_current_track().await {
-        Ok(track_info) => {
+    match result {
+        Ok(pending) => {
+            let track_info = &pending.track_info;
             let _ = Notification::new()
                 .summary("❤️ Liked!")
-                .body(&format!("✅ Verified: {} - {}", track_info.name, track_info.artist))
+                .body(&format!("{} - {}", track_info.name, track_info.artist))
                 .timeout(3000)
                 .show();
             info!("Liked track: {} - {}", track_info.name, track_info.artist);
+            tokio::spawn(verify_in_background(Arc::clone(&spotify_manager), pending));
         }
+        Err(e) if action_queue::is_offline(&e) => queue_offline_action(&*spotify_manager.lock().await, QueuedAction::Like, &e),
         Err(e) => {
             error!("Failed to like track: {}", e);
             let _ = Notification::new()
                 .summary("❌ Failed to like track")
-                .body(&e.to_string())
+                .body(&error_message(&e))
                 .timeout(3000)
                 .show();
         }
     }
 }
 
-async fn handle_unlike_track(spotify_manager: Arc<Mutex<SpotifyManager>>) {
-    let mut manager = spotify_manager.lock().await;
+async fn handle_unlike_track(spotify_manager: Arc<Mutex<SpotifyManager>>, rate_limiter: Arc<RateLimiter>) {
+    let _priority = rate_limiter.user_action();
+    let result = SpotifyManager::with_consent(&spotify_manager, |manager| Box::pin(manager.unlike_current_track_optimistic())).await;
     
-    match manager.unlike_current_track().await {
-        Ok(track_info) => {
+    match result {
+        Ok(pending) => {
+            let track_info = &pending.track_info;
             let _ = Notification::new()
                 .summary("💔 Removed!")
-                .body(&format!("✅ Verified: {} - {}", track_info.name, track_info.artist))
+                .body(&format!("{} - {}", track_info.name, track_info.artist))
                 .timeout(3000)
                 .show();
             info!("Unliked track: {} - {}", track_info.name, track_info.artist);
+            tokio::spawn(verify_in_background(Arc::clone(&spotify_manager), pending));
         }
+        Err(e) if action_queue::is_offline(&e) => queue_offline_action(&*spotify_manager.lock().await, QueuedAction::Unlike, &e),
         Err(e) => {
             error!("Failed to unlike track: {}", e);
             let _ = Notification::new()
                 .summary("❌ Failed to remove track")
-                .body(&e.to_string())
+                .body(&error_message(&e))
                 .timeout(3000)
                 .show();
         }
     }
 }
 
-async fn handle_save_track(spotify_manager: Arc<Mutex<SpotifyManager>>) {
-    let mut manager = spotify_manager.lock().await;
+async fn handle_toggle_like(spotify_manager: Arc<Mutex<SpotifyManager>>, rate_limiter: Arc<RateLimiter>) {
+    let _priority = rate_limiter.user_action();
+    let result = SpotifyManager::with_consent(&spotify_manager, |manager| Box::pin(manager.toggle_like_current_track())).await;
     
-    match manager.save_current_track().await {
-        Ok(track_info) => {
+    match result {
+        Ok(pending) => {
+            let track_info = &pending.track_info;
+            let summary = if pending.liked { "❤️ Now liked" } else { "💔 No longer liked" };
+            let _ = Notification::new()
+                .summary(summary)
+                .body(&format!("{} - {}", track_info.name, track_info.artist))
+                .timeout(3000)
+                .show();
+            info!("Toggled track to {}: {} - {}", if pending.liked { "liked" } else { "not liked" }, track_info.name, track_info.artist);
+            tokio::spawn(verify_in_background(Arc::clone(&spotify_manager), pending));
+        }
+        Err(e) if action_queue::is_offline(&e) => {
+            let manager = spotify_manager.lock().await;
+            match manager.offline_toggle_action() {
+                Ok(action) => queue_offline_action(&manager, action, &e),
+                Err(toggle_error) => {
+                    error!("Failed to queue offline toggle: {}", toggle_error);
+                    let _ = Notification::new()
+                        .summary("❌ Failed to toggle like")
+                        .body(&format!("Not queued: {}", toggle_error))
+                        .timeout(3000)
+                        .show();
+                }
+            }
+        }
+        Err(e) => {
+            error!("Failed to toggle like: {}", e);
+            let _ = Notification::new()
+                .summary("❌ Failed to toggle like")
+                .body(&error_message(&e))
+                .timeout(3000)
+                .show();
+        }
+    }
+}
+
+async fn handle_save_track(spotify_manager: Arc<Mutex<SpotifyManager>>, rate_limiter: Arc<RateLimiter>) {
+    let _priority = rate_limiter.user_action();
+    let result = SpotifyManager::with_consent(&spotify_manager, |manager| Box::pin(manager.save_current_track())).await;
+    
+    match result {
+        Ok(pending) => {
+            let track_info = &pending.track_info;
             let _ = Notification::new()
                 .summary("💾 Saved!")
-                .body(&format!("✅ Verified: {} - {}", track_info.name, track_info.artist))
+                .body(&format!("{} - {}", track_info.name, track_info.artist))
                 .timeout(3000)
                 .show();
             info!("Saved track: {} - {}", track_info.name, track_info.artist);
+            tokio::spawn(verify_in_background(Arc::clone(&spotify_manager), pending));
         }
+        Err(e) if action_queue::is_offline(&e) => queue_offline_action(&*spotify_manager.lock().await, QueuedAction::Like, &e),
         Err(e) => {
             error!("Failed to save track: {}", e);
             let _ = Notification::new()
                 .summary("❌ Failed to save track")
-                .body(&e.to_string())
+                .body(&error_message(&e))
                 .timeout(3000)
                 .show();
         }
     }
 }
 
-async fn handle_show_current_track(spotify_manager: Arc<Mutex<SpotifyManager>>) {
-    let mut manager = spotify_manager.lock().await;
+async fn handle_toggle_playback(spotify_manager: Arc<Mutex<SpotifyManager>>, rate_limiter: Arc<RateLimiter>) {
+    let _priority = rate_limiter.user_action();
+    let result = SpotifyManager::with_consent(&spotify_manager, |manager| Box::pin(manager.toggle_playback())).await;
     
-    match manager.get_current_track().await {
+    match result {
+        Ok(is_playing) => {
+            let (summary, body) = if is_playing { ("▶️ Playing", "Playback resumed") } else { ("⏸️ Paused", "Playback paused") };
+            let _ = Notification::new()
+                .summary(summary)
+                .body(body)
+                .timeout(3000)
+                .show();
+        }
+        Err(e) => notify_playback_error("toggle playback", &e),
+    }
+}
+
+async fn handle_skip_track(spotify_manager: Arc<Mutex<SpotifyManager>>, rate_limiter: Arc<RateLimiter>, forward: bool) {
+    let _priority = rate_limiter.user_action();
+    let result = SpotifyManager::with_consent(&spotify_manager, |manager| {
+        if forward { Box::pin(manager.next_track()) } else { Box::pin(manager.previous_track()) }
+    }).await;
+    
+    match result {
+        Ok(()) => {
+            let (summary, body) = if forward { ("⏭️ Next track", "Skipped to the next track") } else { ("⏮️ Previous track", "Went back to the previous track") };
+            let _ = Notification::new()
+                .summary(summary)
+                .body(body)
+                .timeout(3000)
+                .show();
+        }
+        Err(e) => notify_playback_error(if forward { "skip to the next track" } else { "go back to the previous track" }, &e),
+    }
+}
+
+/// Apply volume presses until none are left, each batch in a single API call
+async fn handle_volume_change(spotify_manager: Arc<Mutex<SpotifyManager>>, rate_limiter: Arc<RateLimiter>, coalescer: Arc<VolumeCoalescer>) {
+    loop {
+        tokio::time::sleep(playback::VOLUME_COALESCE_WINDOW).await;
+        let Some(delta) = coalescer.take() else {
+            break;
+        };
+        
+        let _priority = rate_limiter.user_action();
+        let result = SpotifyManager::with_consent(&spotify_manager, |manager| Box::pin(manager.change_volume(delta))).await;
+        
+        match result {
+            Ok(volume) => {
+                let _ = Notification::new()
+                    .summary(&format!("🔊 Volume {}%", volume))
+                    .timeout(1500)
+                    .show();
+            }
+            Err(e) => notify_playback_error("change the volume", &e),
+        }
+    }
+}
+
+async fn handle_toggle_mute(spotify_manager: Arc<Mutex<SpotifyManager>>, rate_limiter: Arc<RateLimiter>) {
+    let _priority = rate_limiter.user_action();
+    let result = SpotifyManager::with_consent(&spotify_manager, |manager| Box::pin(manager.toggle_mute())).await;
+    
+    match result {
+        Ok(0) => {
+            let _ = Notification::new()
+                .summary("🔇 Muted")
+                .timeout(1500)
+                .show();
+        }
+        Ok(volume) => {
+            let _ = Notification::new()
+                .summary(&format!("🔊 Volume {}%", volume))
+                .body("Unmuted")
+                .timeout(1500)
+                .show();
+        }
+        Err(e) => notify_playback_error("toggle mute", &e),
+    }
+}
+
+/// Seek `offset_ms` forward, or back if negative
+async fn handle_seek(spotify_manager: Arc<Mutex<SpotifyManager>>, rate_limiter: Arc<RateLimiter>, offset_ms: i64) {
+    let _priority = rate_limiter.user_action();
+    let result = SpotifyManager::with_consent(&spotify_manager, |manager| Box::pin(manager.seek_by(offset_ms))).await;
+    
+    match result {
+        Ok(position) => {
+            let _ = Notification::new()
+                .summary(&format!("{} {}", if offset_ms < 0 { "⏪" } else { "⏩" }, position))
+                .timeout(1500)
+                .show();
+        }
+        Err(e) => notify_playback_error("seek", &e),
+    }
+}
+
+async fn handle_restart_track(spotify_manager: Arc<Mutex<SpotifyManager>>, rate_limiter: Arc<RateLimiter>) {
+    let _priority = rate_limiter.user_action();
+    let result = SpotifyManager::with_consent(&spotify_manager, |manager| Box::pin(manager.restart_track())).await;
+    
+    match result {
+        Ok(_) => {
+            let _ = Notification::new()
+                .summary("↩️ Restarted")
+                .body("Playing from the start")
+                .timeout(1500)
+                .show();
+        }
+        Err(e) => notify_playback_error("restart the track", &e),
+    }
+}
+
+async fn handle_toggle_shuffle(spotify_manager: Arc<Mutex<SpotifyManager>>, rate_limiter: Arc<RateLimiter>, tx: mpsc::UnboundedSender<AppMessage>) {
+    let _priority = rate_limiter.user_action();
+    let result = SpotifyManager::with_consent(&spotify_manager, |manager| Box::pin(manager.toggle_shuffle())).await;
+    
+    match result {
+        Ok(modes) => {
+            let _ = tx.send(AppMessage::UpdatePlayerModes(modes));
+            let _ = Notification::new()
+                .summary(if modes.shuffle { "🔀 Shuffle on" } else { "➡️ Shuffle off" })
+                .timeout(1500)
+                .show();
+        }
+        Err(e) => notify_playback_error("toggle shuffle", &e),
+    }
+}
+
+async fn handle_cycle_repeat(spotify_manager: Arc<Mutex<SpotifyManager>>, rate_limiter: Arc<RateLimiter>, tx: mpsc::UnboundedSender<AppMessage>) {
+    let _priority = rate_limiter.user_action();
+    let result = SpotifyManager::with_consent(&spotify_manager, |manager| Box::pin(manager.cycle_repeat())).await;
+    
+    match result {
+        Ok(modes) => {
+            let _ = tx.send(AppMessage::UpdatePlayerModes(modes));
+            let _ = Notification::new()
+                .summary(playback::repeat_label(modes.repeat))
+                .timeout(1500)
+                .show();
+        }
+        Err(e) => notify_playback_error("change the repeat mode", &e),
+    }
+}
+
+async fn handle_transfer_playback(
+    spotify_manager: Arc<Mutex<SpotifyManager>>,
+    rate_limiter: Arc<RateLimiter>,
+    device: Device,
+    tx: mpsc::UnboundedSender<AppMessage>,
+) {
+    let Some(device_id) = device.id.as_deref() else {
+        return;
+    };
+    
+    let _priority = rate_limiter.user_action();
+    let result = SpotifyManager::with_consent(&spotify_manager, |manager| {
+        let device_id = device_id.to_string();
+        Box::pin(async move { manager.transfer_playback(&device_id).await })
+    }).await;
+    let devices = match &result {
+        Ok(()) => spotify_manager.lock().await.devices().await.ok(),
+        Err(_) => None,
+    };
+    
+    match result {
+        Ok(()) => {
+            if let Some(devices) = devices {
+                let _ = tx.send(AppMessage::UpdateDevices(devices));
+            }
+            let _ = Notification::new()
+                .summary("🔈 Switched device")
+                .body(&format!("Now playing on {}", device.name))
+                .timeout(3000)
+                .show();
+        }
+        Err(e) => notify_playback_error(&format!("switch to {}", device.name), &e),
+    }
+}
+
+/// Move playback to the configured preferred device at startup, if nothing is playing
+async fn handle_preferred_device(spotify_manager: Arc<Mutex<SpotifyManager>>, preferred: String) {
+    let result = spotify_manager.lock().await.transfer_to_preferred_device(&preferred).await;
+    
+    match result {
+        Ok(Some(device)) => {
+            info!("🔈 Moved playback to preferred device {}", device.name);
+            let _ = Notification::new()
+                .summary("🔈 Switched device")
+                .body(&format!("Spotify will play on {}", device.name))
+                .timeout(3000)
+                .show();
+        }
+        Ok(None) => {}
+        Err(e) => warn!("⚠️ Couldn't switch to preferred device \"{}\": {}", preferred, e),
+    }
+}
+
+/// Report a failed player command, explaining a missing device instead of showing the API error
+fn notify_playback_error(action: &str, e: &anyhow::Error) {
+    error!("Failed to {}: {}", action, e);
+    
+    let (summary, body) = match e.downcast_ref::<NoActiveDevice>() {
+        Some(no_device) => ("📵 No active Spotify device".to_string(), no_device.to_string()),
+        None => (format!("❌ Failed to {}", action), error_message(e)),
+    };
+    let _ = Notification::new()
+        .summary(&summary)
+        .body(&body)
+        .timeout(3000)
+        .show();
+}
+
+async fn handle_show_current_track(spotify_manager: Arc<Mutex<SpotifyManager>>, rate_limiter: Arc<RateLimiter>) {
+    let _priority = rate_limiter.user_action();
+    let result = SpotifyManager::with_consent(&spotify_manager, |manager| Box::pin(manager.get_current_track())).await;
+    
+    match result {
         Ok(track_info) => {
             info!("Current track: {} - {}", track_info.name, track_info.artist);
         }
@@ -367,16 +1026,195 @@ async fn handle_show_current_track(spotify_manager: Arc<Mutex<SpotifyManager>>)
     }
 }
 
-fn handle_show_hotkey_info() {
+async fn handle_switch_profile(
+    spotify_manager: Arc<Mutex<SpotifyManager>>,
+    config: Arc<Mutex<AppConfig>>,
+    profile: String,
+    tx: mpsc::UnboundedSender<AppMessage>,
+) {
+    let mut new_config = config.lock().await.clone();
+    let previous = new_config.active_profile_name().to_string();
+    
+    if profile == previous {
+        let _ = tx.send(AppMessage::UpdateActiveProfile(previous));
+        return;
+    }
+    
+    info!("Switching Spotify profile: {} -> {}", previous, profile);
+    new_config.active_profile = Some(profile.clone());
+    
+    // Authenticate without holding the manager lock, this may wait for a browser login
+    match SpotifyManager::new(&new_config).await {
+        Ok(mut new_manager) => {
+            let mut manager = spotify_manager.lock().await;
+            new_manager.share_rate_limiter(manager.rate_limiter());
+            *manager = new_manager;
+            drop(manager);
+            
+            if let Err(e) = new_config.save() {
+                warn!("Failed to remember active profile: {}", e);
+            }
+            *config.lock().await = new_config;
+            
+            // Import the new profile's library right away instead of at the next interval
+            let library_manager = Arc::clone(&spotify_manager);
+            tokio::spawn(async move {
+                if let Err(e) = library::sync(&library_manager).await {
+                    warn!("⚠️ Liked Songs library sync failed: {}", e);
+                }
+            });
+            
+            let _ = tx.send(AppMessage::UpdateActiveProfile(profile.clone()));
+            let _ = Notification::new()
+                .summary("👤 Account switched")
+                .body(&format!("Now using profile \"{}\"", profile))
+                .timeout(3000)
+                .show();
+        }
+        Err(e) => {
+            error!("Failed to switch to profile {}: {}", profile, e);
+            let _ = tx.send(AppMessage::UpdateActiveProfile(previous));
+            let _ = Notification::new()
+                .summary("❌ Failed to switch account")
+                .body(&format!("Profile \"{}\": {}", profile, e))
+                .timeout(3000)
+                .show();
+        }
+    }
+}
+
+/// Finish verifying an optimistic like or unlike, notifying only if it didn't stick
+async fn verify_in_background(spotify_manager: Arc<Mutex<SpotifyManager>>, pending: PendingVerification) {
+    let liked = pending.liked;
+    let Some(result) = SpotifyManager::verify_pending(&spotify_manager, pending).await else {
+        return;
+    };
+    if result.success {
+        return;
+    }
+    
+    let (summary, body) = if liked {
+        ("⚠️ Like didn't stick", "was not saved to your library, try again")
+    } else {
+        ("⚠️ Unlike didn't stick", "is still in your library, try again")
+    };
+    let _ = Notification::new()
+        .summary(summary)
+        .body(&format!("{} - {} {}", result.subject.name, result.subject.artist, body))
+        .timeout(5000)
+        .show();
+}
+
+/// Keep a like or unlike that couldn't reach Spotify, the replay task applies it later
+fn queue_offline_action(manager: &SpotifyManager, action: QueuedAction, e: &anyhow::Error) {
+    warn!("📴 Spotify unreachable, queueing {}: {}", action, e);
+    
+    match manager.queue_action(action) {
+        Ok(pending) => {
+            let _ = Notification::new()
+                .summary(&format!("📥 Offline - {} queued", action))
+                .body(&format!("{} - {} will be {}d once Spotify is reachable again", pending.name, pending.artist, action))
+                .timeout(3000)
+                .show();
+        }
+        Err(queue_error) => {
+            error!("Failed to queue offline {}: {}", action, queue_error);
+            let _ = Notification::new()
+                .summary(&format!("❌ Failed to {} track", action))
+                .body(&format!("Not queued: {}", queue_error))
+                .timeout(3000)
+                .show();
+        }
+    }
+}
+
+/// Tell the user what happened to the actions queued while offline
+fn notify_replay(report: &ReplayReport) {
+    let applied = report.applied.iter().map(|pending| {
+        let icon = match pending.action {
+            QueuedAction::Like => "❤️ Liked",
+            QueuedAction::Unlike => "💔 Removed",
+        };
+        format!("{}: {} - {}", icon, pending.name, pending.artist)
+    });
+    let failed = report.failed.iter().map(|(pending, reason)| {
+        format!("❌ Couldn't {} {} - {}: {}", pending.action, pending.name, pending.artist, reason)
+    });
+    
+    let _ = Notification::new()
+        .summary("📤 Offline actions synced")
+        .body(&applied.chain(failed).collect::<Vec<_>>().join("\n"))
+        .timeout(5000)
+        .show();
+}
+
+/// Text for an error notification, surfacing a rate limit hidden behind context
+fn error_message(e: &anyhow::Error) -> String {
+    match e.downcast_ref::<RateLimited>() {
+        Some(limited) => limited.to_string(),
+        None => e.to_string(),
+    }
+}
+
+/// Register a global hotkey from its text, `name` being its setting for errors and logs
+fn register_hotkey(hotkey_manager: &GlobalHotKeyManager, name: &str, text: &str) -> Result<HotKey> {
+    let hotkey = HotKey::from_str(text)
+        .with_context(|| format!("Invalid hotkey {} = \"{}\"", name, text))?;
+    hotkey_manager
+        .register(hotkey)
+        .with_context(|| format!("Failed to register {} hotkey ({})", name, text))?;
+    info!("Registered global hotkey: {} ({})", text, name);
+    Ok(hotkey)
+}
+
+/// What a configured hotkey does and how it is described, by its setting name in `[hotkeys]`
+fn hotkey_action(name: &str) -> Option<(AppMessage, &'static str)> {
+    match name {
+        "like_track" => Some((AppMessage::LikeCurrentTrack, "Like current track")),
+        "unlike_track" => Some((AppMessage::UnlikeCurrentTrack, "Unlike current track")),
+        "toggle_like" => Some((AppMessage::ToggleLike, "Toggle like on current track")),
+        "play_pause" => Some((AppMessage::TogglePlayback, "Play/pause")),
+        "next_track" => Some((AppMessage::NextTrack, "Next track")),
+        "previous_track" => Some((AppMessage::PreviousTrack, "Previous track")),
+        "volume_up" => Some((AppMessage::VolumeUp, "Volume up")),
+        "volume_down" => Some((AppMessage::VolumeDown, "Volume down")),
+        "mute" => Some((AppMessage::ToggleMute, "Mute/unmute")),
+        "seek_forward" => Some((AppMessage::SeekForward, "Seek forward")),
+        "seek_backward" => Some((AppMessage::SeekBackward, "Seek backward")),
+        "restart_track" => Some((AppMessage::RestartTrack, "Restart track")),
+        "toggle_shuffle" => Some((AppMessage::ToggleShuffle, "Toggle shuffle")),
+        "cycle_repeat" => Some((AppMessage::CycleRepeat, "Cycle repeat mode")),
+        _ => None,
+    }
+}
+
+fn handle_show_hotkey_info(hotkey_config: &HotkeyConfig) {
+    let descriptions = hotkey_config.bindings().into_iter().filter_map(|(name, hotkey)| {
+        hotkey_action(name).map(|(_, description)| format!("{} - {}", hotkey, description))
+    });
+    let seek_descriptions = hotkey_config.seek.iter().map(|seek| format!("{} - Seek {:+}s", seek.hotkey, seek.seconds));
+    let descriptions = descriptions.chain(seek_descriptions);
     let _ = Notification::new()
         .summary("🎹 Spotify Quick Actions - Hotkeys")
-        .body("Ctrl+Alt+L - Like current track\nCtrl+Alt+U - Unlike current track\n\nRight-click tray icon for more options!")
+        .body(&format!("{}\n\nRight-click tray icon for more options!", descriptions.collect::<Vec<_>>().join("\n")))
         .timeout(8000)  // Show for 8 seconds
         .show();
     
     info!("Displayed hotkey information to user");
 }
 
+async fn handle_show_stats(spotify_manager: Arc<Mutex<SpotifyManager>>) {
+    let stats = spotify_manager.lock().await.stats();
+    let summary = stats.summary();
+    
+    info!("📊 Stats:\n{}", summary);
+    let _ = Notification::new()
+        .summary("📊 Spotify Quick Actions - Stats")
+        .body(&summary.to_string())
+        .timeout(8000)
+        .show();
+}
+
 async fn handle_toggle_autostart(tx: mpsc::UnboundedSender<AppMessage>) {
     #[cfg(windows)]
     {
@@ -0,0 +1,1006 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use rspotify::{model::RepeatState, Config, OAuth, Token};
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+use url::Url;
+
+use crate::auth_client::AuthClient;
+use crate::config::AuthFlow;
+use crate::permissions;
+use crate::playback::PlayerModes;
+use crate::token_store::{self, TokenStore};
+
+const REFRESH_TOKEN: &str = "mock-refresh-token";
+
+/// Upper bound for a request we are willing to buffer
+const MAX_REQUEST_BYTES: usize = 64 * 1024;
+
+/// Local stand-in for the Spotify Web API and Accounts service
+///
+/// Serves the endpoints the app uses over real HTTP, so tests exercise rspotify's
+/// request and error handling. Scenarios (read lag, expired tokens, rate limits,
+/// empty playback) are scripted through the methods below while the server runs.
+pub struct MockSpotify {
+    addr: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+    server: JoinHandle<()>,
+}
+
+#[derive(Default)]
+struct MockState {
+    access_token: String,
+    token_generation: u32,
+    refreshes: u32,
+    playing: Option<Value>,
+    /// Volume of the active device, `None` if it can't be controlled
+    volume: Option<u32>,
+    modes: PlayerModes,
+    /// Liked Songs with their added_at
+    liked: HashMap<String, DateTime<Utc>>,
+    /// Library as reads last saw it, served while they lag behind the writes
+    stale_liked: HashMap<String, DateTime<Utc>>,
+    lagging_reads: u32,
+    read_lag: u32,
+    rate_limited: u32,
+    retry_after_secs: u64,
+    /// Drop every connection without answering, like a lost network
+    offline: bool,
+    /// Player commands fail with 404 like when no Spotify app is open
+    no_active_device: bool,
+    /// Devices by ID and name, besides "mock-device" which plays `playing`
+    devices: Vec<(String, String)>,
+    /// Device playback was transferred to
+    active_device: Option<String>,
+    requests: Vec<String>,
+}
+
+impl MockState {
+    fn issue_access_token(&mut self) -> String {
+        self.token_generation += 1;
+        self.access_token = format!("mock-access-token-{}", self.token_generation);
+        self.access_token.clone()
+    }
+
+    fn active_device_id(&self) -> Option<&str> {
+        let playing_device = (self.playing.is_some() && !self.no_active_device).then_some("mock-device");
+        self.active_device.as_deref().or(playing_device)
+    }
+
+    fn device_list(&self) -> Vec<Value> {
+        let playing_device = (self.playing.is_some() && !self.no_active_device)
+            .then(|| ("mock-device".to_string(), "Mock Device".to_string()));
+        playing_device.iter()
+            .chain(&self.devices)
+            .map(|(id, name)| device_json(id, name, self.active_device_id() == Some(id.as_str()), self.volume))
+            .collect()
+    }
+}
+
+impl MockSpotify {
+    /// Start the server on a free loopback port
+    pub async fn start() -> Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
+        let addr = listener.local_addr()?;
+
+        let mut state = MockState::default();
+        state.issue_access_token();
+        let state = Arc::new(Mutex::new(state));
+
+        let server = tokio::spawn(serve(listener, Arc::clone(&state)));
+
+        Ok(Self { addr, state, server })
+    }
+
+    /// Value for rspotify's `Config::api_base_url`
+    pub fn api_base_url(&self) -> String {
+        format!("http://{}/v1/", self.addr)
+    }
+
+    /// Value for rspotify's `Config::auth_base_url`
+    pub fn accounts_base_url(&self) -> String {
+        format!("http://{}/", self.addr)
+    }
+
+    /// A token the server currently accepts
+    pub fn token(&self) -> Token {
+        Token {
+            access_token: self.state.lock().unwrap().access_token.clone(),
+            expires_in: ChronoDuration::hours(1),
+            expires_at: Some(Utc::now() + ChronoDuration::hours(1)),
+            refresh_token: Some(REFRESH_TOKEN.to_string()),
+            scopes: permissions::all_scopes(),
+        }
+    }
+
+    /// An authenticated rspotify-backed client talking to this server
+    pub async fn client(&self) -> Result<AuthClient> {
+        self.client_with_store(Arc::new(MemoryTokenStore::new(Some(self.token())))).await
+    }
+
+    /// Like `client`, starting from whatever token `store` holds
+    pub async fn client_with_store(&self, store: Arc<dyn TokenStore>) -> Result<AuthClient> {
+        let config = Config {
+            api_base_url: self.api_base_url(),
+            auth_base_url: self.accounts_base_url(),
+            token_cached: false,
+            token_refreshing: false,
+            token_callback_fn: Arc::new(Some(token_store::persist_callback(Arc::clone(&store)))),
+            ..Default::default()
+        };
+        let oauth = OAuth {
+            redirect_uri: "http://127.0.0.1:8888/callback".to_string(),
+            scopes: permissions::all_scopes(),
+            ..Default::default()
+        };
+
+        let mut client = AuthClient::new(AuthFlow::Code, "mock-client-id", "mock-client-secret", oauth, config, store);
+        client.ensure_authenticated().await?;
+        Ok(client)
+    }
+
+    /// Start playing a track
+    pub fn play(&self, id: &str, name: &str, artist: &str) {
+        self.state.lock().unwrap().playing = Some(currently_playing_json(id, name, artist));
+    }
+
+    /// Stop playback, `currently-playing` answers 204 No Content
+    pub fn stop(&self) {
+        self.state.lock().unwrap().playing = None;
+    }
+
+    pub fn is_liked(&self, id: &str) -> bool {
+        self.state.lock().unwrap().liked.contains_key(id)
+    }
+
+    /// Put a track in Liked Songs as if it was liked at `added_at`
+    pub fn like(&self, id: &str, added_at: DateTime<Utc>) {
+        self.state.lock().unwrap().liked.insert(id.to_string(), added_at);
+    }
+
+    /// Position within the playing track, `None` if nothing is playing
+    pub fn progress_ms(&self) -> Option<u64> {
+        self.state.lock().unwrap().playing.as_ref().and_then(|playing| playing["progress_ms"].as_u64())
+    }
+
+    /// Volume the active device reports
+    pub fn set_volume(&self, volume: u32) {
+        self.state.lock().unwrap().volume = Some(volume);
+    }
+
+    pub fn volume(&self) -> Option<u32> {
+        self.state.lock().unwrap().volume
+    }
+
+    pub fn modes(&self) -> PlayerModes {
+        self.state.lock().unwrap().modes
+    }
+
+    /// Another Spotify Connect device the user could play on
+    pub fn add_device(&self, id: &str, name: &str) {
+        self.state.lock().unwrap().devices.push((id.to_string(), name.to_string()));
+    }
+
+    pub fn active_device(&self) -> Option<String> {
+        self.state.lock().unwrap().active_device_id().map(str::to_string)
+    }
+
+    /// Close every Spotify app, player commands answer 404
+    pub fn disconnect_devices(&self) {
+        self.state.lock().unwrap().no_active_device = true;
+    }
+
+    /// Drop every connection unanswered until `go_online`
+    pub fn go_offline(&self) {
+        self.state.lock().unwrap().offline = true;
+    }
+
+    pub fn go_online(&self) {
+        self.state.lock().unwrap().offline = false;
+    }
+
+    /// Keep answering `contains` with the old state for `reads` calls after each write
+    pub fn lag_saved_tracks(&self, reads: u32) {
+        self.state.lock().unwrap().read_lag = reads;
+    }
+
+    /// Rotate the access token, so requests get 401 until the client refreshes
+    pub fn expire_access_token(&self) {
+        self.state.lock().unwrap().issue_access_token();
+    }
+
+    /// Answer the next `requests` API calls with 429 and the given `Retry-After`
+    pub fn rate_limit(&self, requests: u32, retry_after_secs: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.rate_limited = requests;
+        state.retry_after_secs = retry_after_secs;
+    }
+
+    /// How often the token endpoint handed out a refreshed token
+    pub fn token_refreshes(&self) -> u32 {
+        self.state.lock().unwrap().refreshes
+    }
+
+    /// Every request seen so far as "METHOD /path", without query
+    pub fn requests(&self) -> Vec<String> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Number of requests that match "METHOD /path"
+    pub fn count(&self, request: &str) -> usize {
+        self.requests().iter().filter(|r| *r == request).count()
+    }
+}
+
+impl Drop for MockSpotify {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+/// TokenStore that only lives as long as the test
+#[derive(Default)]
+pub struct MemoryTokenStore {
+    token: Mutex<Option<Token>>,
+}
+
+impl MemoryTokenStore {
+    pub fn new(token: Option<Token>) -> Self {
+        Self { token: Mutex::new(token) }
+    }
+}
+
+impl TokenStore for MemoryTokenStore {
+    fn load(&self) -> Result<Option<Token>> {
+        Ok(self.token.lock().unwrap().clone())
+    }
+
+    fn save(&self, token: &Token) -> Result<()> {
+        *self.token.lock().unwrap() = Some(token.clone());
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<()> {
+        *self.token.lock().unwrap() = None;
+        Ok(())
+    }
+
+    fn location(&self) -> String {
+        "memory".to_string()
+    }
+}
+
+/// Minimal `currently-playing` payload for a track
+pub fn currently_playing_json(id: &str, name: &str, artist: &str) -> Value {
+    json!({
+        "context": null,
+        "timestamp": 0,
+        "progress_ms": 0,
+        "is_playing": true,
+        "currently_playing_type": "track",
+        "actions": { "disallows": {} },
+        "item": track_json(id, name, artist),
+    })
+}
+
+/// Playback state as `GET /me/player` returns it, for a currently playing payload
+pub fn playback_json(playing: &Value, device: Value, modes: PlayerModes) -> Value {
+    let mut playback = playing.clone();
+    playback["device"] = device;
+    playback["shuffle_state"] = json!(modes.shuffle);
+    playback["repeat_state"] = json!(modes.repeat);
+    playback
+}
+
+/// A Spotify Connect device
+pub fn device_json(id: &str, name: &str, is_active: bool, volume: Option<u32>) -> Value {
+    json!({
+        "id": id,
+        "is_active": is_active,
+        "is_private_session": false,
+        "is_restricted": false,
+        "name": name,
+        "type": "Computer",
+        "volume_percent": volume,
+    })
+}
+
+/// Minimal full track object
+pub fn track_json(id: &str, name: &str, artist: &str) -> Value {
+    json!({
+        "album": {
+            "artists": [],
+            "available_markets": [],
+            "external_urls": {},
+            "images": [],
+            "name": "Test Album",
+        },
+        "artists": [{ "external_urls": {}, "name": artist }],
+        "available_markets": [],
+        "disc_number": 1,
+        "duration_ms": 180000,
+        "explicit": false,
+        "external_ids": {},
+        "external_urls": {},
+        "href": null,
+        "id": id,
+        "is_local": false,
+        "name": name,
+        "popularity": 0,
+        "preview_url": null,
+        "track_number": 1,
+        "type": "track",
+    })
+}
+
+/// One page of saved tracks out of `saved` (newest first), named after their IDs
+pub fn saved_tracks_page_json(saved: &[(String, DateTime<Utc>)], limit: usize, offset: usize) -> Value {
+    let items: Vec<Value> = saved.iter()
+        .skip(offset)
+        .take(limit)
+        .map(|(id, added_at)| json!({
+            "added_at": added_at.to_rfc3339(),
+            "track": track_json(id, id, "Test Artist"),
+        }))
+        .collect();
+    let next = (offset + limit < saved.len())
+        .then(|| format!("https://api.spotify.com/v1/me/tracks?offset={}&limit={}", offset + limit, limit));
+
+    json!({
+        "href": "https://api.spotify.com/v1/me/tracks",
+        "items": items,
+        "limit": limit,
+        "next": next,
+        "offset": offset,
+        "previous": null,
+        "total": saved.len(),
+    })
+}
+
+/// Minimal `/me` payload
+pub fn user_json() -> Value {
+    json!({
+        "display_name": "Test User",
+        "external_urls": {},
+        "href": "https://api.spotify.com/v1/users/test",
+        "id": "test",
+    })
+}
+
+struct Request {
+    method: String,
+    url: Url,
+    authorization: Option<String>,
+    body: String,
+}
+
+struct Response {
+    status: &'static str,
+    headers: Vec<(&'static str, String)>,
+    body: String,
+}
+
+impl Response {
+    fn json(status: &'static str, body: Value) -> Self {
+        Self { status, headers: Vec::new(), body: body.to_string() }
+    }
+
+    fn empty(status: &'static str) -> Self {
+        Self { status, headers: Vec::new(), body: String::new() }
+    }
+
+    fn error(status: &'static str, code: u16, message: &str) -> Self {
+        Self::json(status, json!({ "error": { "status": code, "message": message } }))
+    }
+}
+
+async fn serve(listener: TcpListener, state: Arc<Mutex<MockState>>) {
+    while let Ok((stream, _)) = listener.accept().await {
+        tokio::spawn(handle_connection(stream, Arc::clone(&state)));
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream, state: Arc<Mutex<MockState>>) {
+    if state.lock().unwrap().offline {
+        return;
+    }
+
+    let response = match read_request(&mut stream).await {
+        Ok(request) => route(&mut state.lock().unwrap(), &request),
+        Err(e) => Response::error("400 Bad Request", 400, &e.to_string()),
+    };
+
+    let mut head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+
+    let _ = stream.write_all(head.as_bytes()).await;
+    let _ = stream.write_all(response.body.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+/// Read one request, including a `Content-Length` body
+async fn read_request(stream: &mut TcpStream) -> Result<Request> {
+    let mut buffer = Vec::with_capacity(1024);
+    let mut chunk = [0u8; 1024];
+
+    let head_end = loop {
+        if let Some(pos) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Err(anyhow!("Connection closed before the request head was complete"));
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+        if buffer.len() > MAX_REQUEST_BYTES {
+            return Err(anyhow!("Request too large"));
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..head_end]).into_owned();
+    let mut lines = head.lines();
+    let request_line = lines.next().unwrap_or_default();
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next()) {
+        (Some(method), Some(target)) => (method.to_string(), target.to_string()),
+        _ => return Err(anyhow!("Unexpected request line: {:?}", request_line)),
+    };
+
+    let header = |name: &str| {
+        head.lines()
+            .skip(1)
+            .filter_map(|line| line.split_once(':'))
+            .find(|(key, _)| key.trim().eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.trim().to_string())
+    };
+
+    let content_length: usize = header("content-length")
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0);
+    while buffer.len() < head_end + content_length {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+
+    Ok(Request {
+        method,
+        url: Url::parse("http://localhost")?.join(&target)?,
+        authorization: header("authorization"),
+        body: String::from_utf8_lossy(&buffer[head_end..]).into_owned(),
+    })
+}
+
+fn route(state: &mut MockState, request: &Request) -> Response {
+    // rspotify sends some paths with a trailing slash ("me/tracks/?ids=...")
+    let path = request.url.path().trim_end_matches('/').to_string();
+    state.requests.push(format!("{} {}", request.method, path));
+
+    if path == "/api/token" {
+        return token_response(state, request);
+    }
+
+    if state.rate_limited > 0 {
+        state.rate_limited -= 1;
+        let mut response = Response::error("429 Too Many Requests", 429, "API rate limit exceeded");
+        response.headers.push(("Retry-After", state.retry_after_secs.to_string()));
+        return response;
+    }
+
+    if request.authorization.as_deref() != Some(format!("Bearer {}", state.access_token).as_str()) {
+        return Response::error("401 Unauthorized", 401, "The access token expired");
+    }
+
+    // Player commands need a device to run on, except the transfer to one
+    if state.no_active_device && path.starts_with("/v1/me/player") && path != "/v1/me/player" && request.method != "GET" {
+        return Response::json("404 Not Found", json!({
+            "error": { "status": 404, "message": "Player command failed: No active device found", "reason": "NO_ACTIVE_DEVICE" }
+        }));
+    }
+
+    let ids: Vec<String> = request.url.query_pairs()
+        .find(|(key, _)| key == "ids")
+        .map(|(_, value)| value.split(',').map(str::to_string).collect())
+        .unwrap_or_default();
+
+    match (request.method.as_str(), path.as_str()) {
+        ("GET", "/v1/me") => Response::json("200 OK", user_json()),
+        ("GET", "/v1/me/player/currently-playing") => match &state.playing {
+            Some(playing) => Response::json("200 OK", playing.clone()),
+            None => Response::empty("204 No Content"),
+        },
+        ("GET", "/v1/me/tracks") => {
+            let param = |name: &str, default: usize| {
+                request.url.query_pairs()
+                    .find(|(key, _)| key == name)
+                    .and_then(|(_, value)| value.parse().ok())
+                    .unwrap_or(default)
+            };
+            let mut saved: Vec<(String, DateTime<Utc>)> = state.liked.iter()
+                .map(|(id, added_at)| (id.clone(), *added_at))
+                .collect();
+            saved.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            Response::json("200 OK", saved_tracks_page_json(&saved, param("limit", 20), param("offset", 0)))
+        }
+        ("PUT", "/v1/me/tracks") | ("DELETE", "/v1/me/tracks") => {
+            // Reads keep serving the state from before the first write they haven't caught up with
+            if state.lagging_reads == 0 {
+                state.stale_liked = state.liked.clone();
+            }
+            state.lagging_reads = state.read_lag;
+            for id in ids {
+                if request.method == "PUT" {
+                    state.liked.insert(id, Utc::now());
+                } else {
+                    state.liked.remove(&id);
+                }
+            }
+            Response::empty("200 OK")
+        }
+        ("GET", "/v1/me/tracks/contains") => {
+            let library = if state.lagging_reads > 0 {
+                state.lagging_reads -= 1;
+                &state.stale_liked
+            } else {
+                &state.liked
+            };
+            let saved: Vec<bool> = ids.iter().map(|id| library.contains_key(id)).collect();
+            Response::json("200 OK", json!(saved))
+        }
+        ("GET", "/v1/me/player") => {
+            let device = state.device_list().into_iter().find(|device| device["is_active"] == json!(true));
+            match (&state.playing, device) {
+                (Some(playing), Some(device)) => Response::json("200 OK", playback_json(playing, device, state.modes)),
+                _ => Response::empty("204 No Content"),
+            }
+        }
+        ("GET", "/v1/me/player/devices") => Response::json("200 OK", json!({ "devices": state.device_list() })),
+        ("PUT", "/v1/me/player") => {
+            let body: Value = serde_json::from_str(&request.body).unwrap_or_default();
+            let device_id = body["device_ids"][0].as_str().unwrap_or_default().to_string();
+            if !state.devices.iter().any(|(id, _)| *id == device_id) && state.active_device_id() != Some(device_id.as_str()) {
+                return Response::error("404 Not Found", 404, "Device not found");
+            }
+            state.active_device = Some(device_id);
+            state.no_active_device = false;
+            Response::empty("204 No Content")
+        }
+        ("PUT", "/v1/me/player/volume") => {
+            state.volume = request.url.query_pairs()
+                .find(|(key, _)| key == "volume_percent")
+                .and_then(|(_, value)| value.parse().ok());
+            Response::empty("204 No Content")
+        }
+        ("PUT", "/v1/me/player/shuffle") | ("PUT", "/v1/me/player/repeat") => {
+            let value = request.url.query_pairs()
+                .find(|(key, _)| key == "state")
+                .map(|(_, value)| value.into_owned())
+                .unwrap_or_default();
+            if path.ends_with("shuffle") {
+                state.modes.shuffle = value == "true";
+            } else if let Ok(repeat) = serde_json::from_value::<RepeatState>(json!(value)) {
+                state.modes.repeat = repeat;
+            }
+            Response::empty("204 No Content")
+        }
+        ("PUT", "/v1/me/player/seek") => {
+            let position_ms: Option<u64> = request.url.query_pairs()
+                .find(|(key, _)| key == "position_ms")
+                .and_then(|(_, value)| value.parse().ok());
+            if let (Some(playing), Some(position_ms)) = (state.playing.as_mut(), position_ms) {
+                playing["progress_ms"] = json!(position_ms);
+            }
+            Response::empty("204 No Content")
+        }
+        ("PUT", "/v1/me/player/pause") | ("PUT", "/v1/me/player/play")
+        | ("POST", "/v1/me/player/next") | ("POST", "/v1/me/player/previous") => {
+            if let Some(playing) = state.playing.as_mut() {
+                match path.as_str() {
+                    "/v1/me/player/pause" => playing["is_playing"] = json!(false),
+                    "/v1/me/player/play" => playing["is_playing"] = json!(true),
+                    _ => {}
+                }
+            }
+            Response::empty("204 No Content")
+        }
+        _ => Response::error("404 Not Found", 404, "Service not found"),
+    }
+}
+
+/// Hand out a new access token for an authorization code or refresh token grant
+fn token_response(state: &mut MockState, request: &Request) -> Response {
+    let form = Url::parse(&format!("http://localhost/?{}", request.body));
+    let grant_type = form.as_ref().ok().and_then(|url| {
+        url.query_pairs()
+            .find(|(key, _)| key == "grant_type")
+            .map(|(_, value)| value.into_owned())
+    });
+
+    match grant_type.as_deref() {
+        Some("refresh_token") => state.refreshes += 1,
+        Some("authorization_code") => {}
+        _ => return Response::json("400 Bad Request", json!({ "error": "unsupported_grant_type" })),
+    }
+
+    let scope = permissions::all_scopes().into_iter().collect::<Vec<_>>().join(" ");
+    Response::json("200 OK", json!({
+        "access_token": state.issue_access_token(),
+        "token_type": "Bearer",
+        "expires_in": 3600,
+        "scope": scope,
+        "refresh_token": REFRESH_TOKEN,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action_queue::{self, QueuedAction};
+    use crate::config::VerificationConfig;
+    use crate::library::{self, Library};
+    use crate::playback::NoActiveDevice;
+    use crate::rate_limit::RateLimited;
+    use crate::spotify_client::SpotifyManager;
+    use crate::token_store::{EncryptedFileStore, EncryptionSecret};
+    use std::sync::Arc;
+
+    const TRACK_ID: &str = "4uLU6hMCjMI75M1A2tKUQC";
+
+    async fn manager(mock: &MockSpotify, max_verification_attempts: u32) -> SpotifyManager {
+        SpotifyManager::with_api(mock.client().await.unwrap(), VerificationConfig::for_tests(max_verification_attempts))
+    }
+
+    #[tokio::test]
+    async fn like_round_trips_over_http() {
+        let mock = MockSpotify::start().await.unwrap();
+        mock.play(TRACK_ID, "Never Gonna Give You Up", "Rick Astley");
+        let mut manager = manager(&mock, 3).await;
+
+        let track = manager.like_current_track().await.unwrap();
+
+        assert_eq!(track.name, "Never Gonna Give You Up");
+        assert!(mock.is_liked(TRACK_ID));
+        assert_eq!(mock.count("PUT /v1/me/tracks"), 1);
+
+        manager.unlike_current_track().await.unwrap();
+
+        assert!(!mock.is_liked(TRACK_ID));
+        assert_eq!(mock.count("DELETE /v1/me/tracks"), 1);
+    }
+
+    #[tokio::test]
+    async fn verification_rides_out_saved_tracks_lag() {
+        let mock = MockSpotify::start().await.unwrap();
+        mock.play(TRACK_ID, "Never Gonna Give You Up", "Rick Astley");
+        mock.lag_saved_tracks(3);
+        let mut manager = manager(&mock, 5).await;
+
+        manager.like_current_track().await.unwrap();
+
+        assert_eq!(mock.count("PUT /v1/me/tracks"), 1);
+        assert_eq!(mock.count("GET /v1/me/tracks/contains"), 4);
+    }
+
+    #[tokio::test]
+    async fn verification_fails_when_lag_outlasts_attempts() {
+        let mock = MockSpotify::start().await.unwrap();
+        mock.play(TRACK_ID, "Never Gonna Give You Up", "Rick Astley");
+        mock.lag_saved_tracks(10);
+        let mut manager = manager(&mock, 2).await;
+
+        let error = manager.like_current_track().await.unwrap_err();
+
+        // The write went through, only the read-back never caught up
+        assert!(error.to_string().contains("failed verification"));
+        assert!(mock.is_liked(TRACK_ID));
+    }
+
+    #[tokio::test]
+    async fn expired_access_token_is_refreshed_and_retried() {
+        let mock = MockSpotify::start().await.unwrap();
+        mock.play(TRACK_ID, "Never Gonna Give You Up", "Rick Astley");
+        let mut manager = manager(&mock, 3).await;
+        mock.expire_access_token();
+
+        let track = manager.get_current_track().await.unwrap();
+
+        assert_eq!(track.artist, "Rick Astley");
+        assert_eq!(mock.token_refreshes(), 1);
+        assert_eq!(mock.count("GET /v1/me/player/currently-playing"), 2);
+    }
+
+    #[tokio::test]
+    async fn user_action_waits_out_retry_after() {
+        let mock = MockSpotify::start().await.unwrap();
+        mock.play(TRACK_ID, "Never Gonna Give You Up", "Rick Astley");
+        let mut manager = manager(&mock, 3).await;
+        mock.rate_limit(1, 1);
+
+        let start = std::time::Instant::now();
+        manager.get_current_track().await.unwrap();
+
+        assert!(start.elapsed() >= std::time::Duration::from_secs(1));
+        assert_eq!(mock.count("GET /v1/me/player/currently-playing"), 2);
+    }
+
+    #[tokio::test]
+    async fn long_retry_after_fails_user_action_without_hammering() {
+        let mock = MockSpotify::start().await.unwrap();
+        mock.play(TRACK_ID, "Never Gonna Give You Up", "Rick Astley");
+        let mut manager = manager(&mock, 3).await;
+        mock.rate_limit(1, 60);
+
+        let error = manager.like_current_track().await.unwrap_err();
+        assert!(error.downcast_ref::<RateLimited>().is_some());
+
+        // Still limited: the next action fails without another request
+        assert!(manager.like_current_track().await.is_err());
+        assert_eq!(mock.count("GET /v1/me/player/currently-playing"), 1);
+        assert_eq!(mock.count("PUT /v1/me/tracks"), 0);
+    }
+
+    #[tokio::test]
+    async fn background_poll_backs_off_while_limited() {
+        let mock = MockSpotify::start().await.unwrap();
+        mock.play(TRACK_ID, "Never Gonna Give You Up", "Rick Astley");
+        let mut manager = manager(&mock, 3).await;
+        mock.rate_limit(1, 1);
+
+        let error = manager.poll_current_track().await.unwrap_err();
+        assert!(error.downcast_ref::<RateLimited>().is_some());
+        assert!(manager.poll_current_track().await.is_err());
+        assert!(manager.rate_limiter().is_limited());
+
+        assert_eq!(mock.count("GET /v1/me/player"), 1);
+    }
+
+    #[tokio::test]
+    async fn empty_playback_reports_no_track() {
+        let mock = MockSpotify::start().await.unwrap();
+        mock.stop();
+        let mut manager = manager(&mock, 3).await;
+
+        let error = manager.like_current_track().await.unwrap_err();
+
+        assert!(error.to_string().contains("No track currently playing"));
+        assert_eq!(mock.count("PUT /v1/me/tracks"), 0);
+    }
+
+    #[tokio::test]
+    async fn library_sync_pages_through_saved_tracks() {
+        let mock = MockSpotify::start().await.unwrap();
+        let start = Utc::now() - chrono::Duration::days(1);
+        for i in 0..75 {
+            mock.like(&format!("track{}", i), start + chrono::Duration::seconds(i));
+        }
+        let library = Arc::new(Library::open_in_memory().unwrap());
+        let mut manager = manager(&mock, 3).await;
+        manager.attach_library(Arc::clone(&library));
+        let manager = tokio::sync::Mutex::new(manager);
+
+        let report = library::sync(&manager).await.unwrap();
+
+        assert_eq!(report.added, 75);
+        assert_eq!(mock.count("GET /v1/me/tracks"), 2);
+        let newest = &library.recent(1).unwrap()[0];
+        assert_eq!(newest.id, "track74");
+        assert_eq!(newest.album, "Test Album");
+        assert_eq!(manager.lock().await.cached_track_liked("spotify:track:track3"), Some(true));
+    }
+
+    #[tokio::test]
+    async fn startup_while_offline_keeps_the_cached_token() {
+        let mock = MockSpotify::start().await.unwrap();
+        let store = Arc::new(MemoryTokenStore::new(Some(mock.token())));
+        mock.go_offline();
+
+        mock.client_with_store(Arc::clone(&store) as Arc<dyn TokenStore>).await.unwrap();
+
+        assert!(store.load().unwrap().is_some());
+        assert_eq!(mock.token_refreshes(), 0);
+    }
+
+    #[tokio::test]
+    async fn startup_while_rate_limited_neither_refreshes_nor_logs_out() {
+        let mock = MockSpotify::start().await.unwrap();
+        let store = Arc::new(MemoryTokenStore::new(Some(mock.token())));
+        mock.rate_limit(1, 1);
+
+        mock.client_with_store(Arc::clone(&store) as Arc<dyn TokenStore>).await.unwrap();
+
+        assert_eq!(store.load().unwrap().unwrap().access_token, mock.token().access_token);
+        assert_eq!(mock.token_refreshes(), 0);
+    }
+
+    #[tokio::test]
+    async fn startup_refreshes_a_rejected_access_token() {
+        let mock = MockSpotify::start().await.unwrap();
+        let store = Arc::new(MemoryTokenStore::new(Some(mock.token())));
+        mock.expire_access_token();
+
+        mock.client_with_store(Arc::clone(&store) as Arc<dyn TokenStore>).await.unwrap();
+
+        assert_eq!(mock.token_refreshes(), 1);
+        assert_eq!(store.load().unwrap().unwrap().access_token, mock.token().access_token);
+    }
+
+    #[tokio::test]
+    async fn startup_with_an_unreadable_cache_fails_and_keeps_the_file() {
+        let mock = MockSpotify::start().await.unwrap();
+        let path = std::env::temp_dir().join(format!("mock_unreadable_{}.enc", std::process::id()));
+        let secret = |passphrase: &str| EncryptionSecret::new(passphrase.as_bytes().to_vec(), "passphrase");
+        EncryptedFileStore::new(path.clone(), secret("secret")).save(&mock.token()).unwrap();
+        let before = std::fs::read(&path).unwrap();
+
+        let store = Arc::new(EncryptedFileStore::new(path.clone(), secret("other")));
+        let err = mock.client_with_store(store).await.err().unwrap();
+        let after = std::fs::read(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert!(format!("{:#}", err).contains("left untouched"), "{:#}", err);
+        assert_eq!(before, after);
+    }
+
+    #[tokio::test]
+    async fn offline_like_is_queued_and_replayed() {
+        let mock = MockSpotify::start().await.unwrap();
+        mock.play(TRACK_ID, "Never Gonna Give You Up", "Rick Astley");
+        let mut manager = manager(&mock, 3).await;
+        manager.get_current_track().await.unwrap();
+        mock.go_offline();
+
+        let error = manager.like_current_track().await.unwrap_err();
+        assert!(action_queue::is_offline(&error));
+        manager.queue_action(QueuedAction::Like).unwrap();
+        manager.queue_action(QueuedAction::Unlike).unwrap();
+        manager.queue_action(QueuedAction::Like).unwrap();
+        let queue = manager.action_queue();
+        let manager = tokio::sync::Mutex::new(manager);
+
+        let report = action_queue::replay(&manager).await;
+        assert!(report.retry_later);
+        assert_eq!(queue.len(), 1);
+
+        mock.go_online();
+        let report = action_queue::replay(&manager).await;
+
+        assert_eq!(report.applied.len(), 1);
+        assert!(report.failed.is_empty() && !report.retry_later);
+        assert!(queue.is_empty());
+        assert!(mock.is_liked(TRACK_ID));
+        assert_eq!(mock.count("PUT /v1/me/tracks"), 1);
+        assert_eq!(mock.count("DELETE /v1/me/tracks"), 0);
+    }
+
+    #[tokio::test]
+    async fn api_errors_are_not_mistaken_for_offline() {
+        let mock = MockSpotify::start().await.unwrap();
+        mock.rate_limit(1, 60);
+        let mut manager = manager(&mock, 3).await;
+
+        let error = manager.like_current_track().await.unwrap_err();
+
+        assert!(!action_queue::is_offline(&error));
+    }
+
+    #[tokio::test]
+    async fn player_commands_round_trip_over_http() {
+        let mock = MockSpotify::start().await.unwrap();
+        mock.play(TRACK_ID, "Never Gonna Give You Up", "Rick Astley");
+        let mut manager = manager(&mock, 3).await;
+
+        assert!(!manager.toggle_playback().await.unwrap());
+        assert!(manager.toggle_playback().await.unwrap());
+        manager.next_track().await.unwrap();
+
+        assert_eq!(mock.count("PUT /v1/me/player/pause"), 1);
+        assert_eq!(mock.count("PUT /v1/me/player/play"), 1);
+        assert_eq!(mock.count("POST /v1/me/player/next"), 1);
+    }
+
+    #[tokio::test]
+    async fn missing_device_is_reported_as_such() {
+        let mock = MockSpotify::start().await.unwrap();
+        mock.disconnect_devices();
+        let mut manager = manager(&mock, 3).await;
+
+        let error = manager.previous_track().await.unwrap_err();
+        assert!(error.downcast_ref::<NoActiveDevice>().is_some());
+
+        // Nothing playing resumes, which needs a device just the same
+        let error = manager.toggle_playback().await.unwrap_err();
+        assert!(error.downcast_ref::<NoActiveDevice>().is_some());
+    }
+
+    #[tokio::test]
+    async fn volume_is_read_from_playback_state_and_set_over_http() {
+        let mock = MockSpotify::start().await.unwrap();
+        mock.play(TRACK_ID, "Never Gonna Give You Up", "Rick Astley");
+        mock.set_volume(40);
+        let mut manager = manager(&mock, 3).await;
+
+        assert_eq!(manager.change_volume(-10).await.unwrap(), 30);
+
+        assert_eq!(mock.volume(), Some(30));
+        assert_eq!(mock.count("GET /v1/me/player"), 1);
+        assert_eq!(mock.count("PUT /v1/me/player/volume"), 1);
+    }
+
+    #[tokio::test]
+    async fn seek_sends_the_clamped_position_over_http() {
+        let mock = MockSpotify::start().await.unwrap();
+        mock.play(TRACK_ID, "Never Gonna Give You Up", "Rick Astley");
+        let mut manager = manager(&mock, 3).await;
+
+        manager.seek_by(-15_000).await.unwrap();
+        assert_eq!(mock.progress_ms(), Some(0));
+
+        manager.seek_by(30_000).await.unwrap();
+        assert_eq!(mock.progress_ms(), Some(30_000));
+        assert_eq!(mock.count("PUT /v1/me/player/seek"), 2);
+    }
+
+    #[tokio::test]
+    async fn shuffle_and_repeat_round_trip_over_http() {
+        let mock = MockSpotify::start().await.unwrap();
+        mock.play(TRACK_ID, "Never Gonna Give You Up", "Rick Astley");
+        let mut manager = manager(&mock, 3).await;
+
+        manager.toggle_shuffle().await.unwrap();
+        manager.cycle_repeat().await.unwrap();
+
+        assert_eq!(mock.modes(), PlayerModes { shuffle: true, repeat: RepeatState::Context });
+        assert_eq!(manager.player_modes().await.unwrap(), Some(mock.modes()));
+        assert_eq!(mock.count("PUT /v1/me/player/shuffle"), 1);
+        assert_eq!(mock.count("PUT /v1/me/player/repeat"), 1);
+    }
+
+    #[tokio::test]
+    async fn poll_reads_track_and_modes_in_one_request() {
+        let mock = MockSpotify::start().await.unwrap();
+        mock.play(TRACK_ID, "Never Gonna Give You Up", "Rick Astley");
+        let mut manager = manager(&mock, 3).await;
+
+        let playback = manager.poll_current_track().await.unwrap();
+
+        assert_eq!(playback.track.unwrap().artist, "Rick Astley");
+        assert_eq!(playback.modes, Some(mock.modes()));
+        assert_eq!(mock.count("GET /v1/me/player"), 1);
+        assert_eq!(mock.count("GET /v1/me/player/currently-playing"), 0);
+    }
+
+    #[tokio::test]
+    async fn devices_are_listed_and_playback_transferred_over_http() {
+        let mock = MockSpotify::start().await.unwrap();
+        mock.play(TRACK_ID, "Never Gonna Give You Up", "Rick Astley");
+        mock.add_device("phone-id", "Phone");
+        let mut manager = manager(&mock, 3).await;
+
+        let devices = manager.devices().await.unwrap();
+        let active: Vec<&str> = devices.iter().filter(|device| device.is_active).map(|device| device.name.as_str()).collect();
+        assert_eq!(devices.len(), 2);
+        assert_eq!(active, ["Mock Device"]);
+
+        manager.transfer_playback("phone-id").await.unwrap();
+        assert_eq!(mock.active_device().as_deref(), Some("phone-id"));
+        assert!(manager.transfer_playback("unknown").await.is_err());
+    }
+}
@@ -0,0 +1,455 @@
+use anyhow::{anyhow, Context, Result};
+use rand::{distributions::Alphanumeric, Rng};
+use std::{
+    fmt,
+    io::ErrorKind,
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    time::timeout,
+};
+use tracing::{info, warn};
+use url::{Host, Url};
+
+/// How long to wait for the browser to come back with the authorization code
+pub const CALLBACK_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Spotify authorization codes are only valid for a short time after login
+///
+/// No longer than `CALLBACK_TIMEOUT`, so a pasted redirect is held to the same limit as the callback.
+pub const AUTHORIZATION_MAX_AGE: Duration = CALLBACK_TIMEOUT;
+
+const STATE_LENGTH: usize = 32;
+
+/// Upper bound for the request head we are willing to read from the browser
+const MAX_REQUEST_BYTES: usize = 16 * 1024;
+
+const SUCCESS_PAGE: &str = "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>Spotify Quick Actions</title></head>\
+<body style=\"font-family: sans-serif; text-align: center; margin-top: 15%;\">\
+<h1>✅ Spotify Quick Actions is connected</h1><p>You can close this tab now.</p></body></html>";
+
+const FAILURE_PAGE: &str = "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>Spotify Quick Actions</title></head>\
+<body style=\"font-family: sans-serif; text-align: center; margin-top: 15%;\">\
+<h1>❌ Authorization failed</h1><p>Check the application log for details. You can close this tab now.</p></body></html>";
+
+/// Query parameters Spotify appended to the redirect URI
+#[derive(Debug, Clone)]
+pub struct CallbackParams {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+/// Why an authorization response was rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthorizationError {
+    /// Spotify reported an `error` instead of a code
+    Spotify { error: String, description: Option<String> },
+    /// No `state` parameter came back
+    MissingState,
+    /// `state` belongs to a different (e.g. earlier) authorization request
+    StateMismatch,
+    /// Neither a code nor an error came back
+    MissingCode,
+    /// A code was already accepted for this request
+    AlreadyRedeemed,
+    /// The response arrived too late for its code to still be exchanged
+    Stale { age_secs: u64 },
+}
+
+impl fmt::Display for AuthorizationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthorizationError::Spotify { error, description } => {
+                let message = match error.as_str() {
+                    "access_denied" => "You declined the authorization request in the browser.".to_string(),
+                    "invalid_client" => "Spotify does not recognize the Client ID - check client_id in config.toml.".to_string(),
+                    "invalid_scope" => "Spotify rejected the requested permissions (invalid scope).".to_string(),
+                    "invalid_request" => "Spotify rejected the authorization request as malformed - check redirect_uri in config.toml and your Spotify app settings.".to_string(),
+                    "unsupported_response_type" => "Spotify does not support the requested authorization type.".to_string(),
+                    "server_error" | "temporarily_unavailable" => "Spotify's login service is having problems, please try again in a moment.".to_string(),
+                    other => format!("Spotify returned an authorization error: {}", other),
+                };
+                match description {
+                    Some(description) => write!(f, "{} ({})", message, description),
+                    None => f.write_str(&message),
+                }
+            }
+            AuthorizationError::MissingState => {
+                f.write_str("The redirect URL has no state parameter. Please make sure you copied the complete URL.")
+            }
+            AuthorizationError::StateMismatch => {
+                f.write_str("The redirect belongs to a different login attempt (state mismatch). Please use the URL from the latest login.")
+            }
+            AuthorizationError::MissingCode => {
+                f.write_str("No authorization code found in the redirect URL. Please make sure you copied the complete URL.")
+            }
+            AuthorizationError::AlreadyRedeemed => {
+                f.write_str("This authorization code has already been used. Please log in again.")
+            }
+            AuthorizationError::Stale { age_secs } => {
+                write!(f, "The login completed {}s ago and its code has expired. Please log in again.", age_secs)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AuthorizationError {}
+
+/// One outstanding authorization request; validates what comes back on the redirect
+#[derive(Debug)]
+pub struct AuthorizationRequest {
+    state: String,
+    issued_at: Instant,
+    max_age: Duration,
+    redeemed: bool,
+}
+
+impl Default for AuthorizationRequest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AuthorizationRequest {
+    /// Start a request with a fresh random state
+    pub fn new() -> Self {
+        let state = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(STATE_LENGTH)
+            .map(char::from)
+            .collect();
+        Self::with_state(state, AUTHORIZATION_MAX_AGE)
+    }
+
+    pub fn with_state(state: String, max_age: Duration) -> Self {
+        Self {
+            state,
+            issued_at: Instant::now(),
+            max_age,
+            redeemed: false,
+        }
+    }
+
+    /// The state value to send with the authorization URL
+    pub fn state(&self) -> &str {
+        &self.state
+    }
+
+    /// Validate a redirect and hand out its code, at most once per request
+    pub fn accept(&mut self, params: &CallbackParams) -> Result<String, AuthorizationError> {
+        // State first, so a forged `error` redirect from another page can't end the login
+        match params.state.as_deref() {
+            None => return Err(AuthorizationError::MissingState),
+            Some(state) if state != self.state => return Err(AuthorizationError::StateMismatch),
+            Some(_) => {}
+        }
+
+        if let Some(error) = &params.error {
+            return Err(AuthorizationError::Spotify {
+                error: error.clone(),
+                description: params.error_description.clone(),
+            });
+        }
+
+        let code = params.code.clone().ok_or(AuthorizationError::MissingCode)?;
+
+        if self.redeemed {
+            return Err(AuthorizationError::AlreadyRedeemed);
+        }
+
+        let age = self.issued_at.elapsed();
+        if age > self.max_age {
+            return Err(AuthorizationError::Stale { age_secs: age.as_secs() });
+        }
+
+        self.redeemed = true;
+        Ok(code)
+    }
+}
+
+/// Check whether the redirect URI points at this machine, so we can serve it ourselves
+pub fn is_loopback_redirect(redirect_uri: &str) -> bool {
+    match Url::parse(redirect_uri) {
+        Ok(url) => url.scheme() == "http" && is_loopback_host(&url),
+        Err(_) => false,
+    }
+}
+
+fn is_loopback_host(url: &Url) -> bool {
+    match url.host() {
+        Some(Host::Domain(domain)) => domain.eq_ignore_ascii_case("localhost"),
+        Some(Host::Ipv4(ip)) => ip.is_loopback(),
+        Some(Host::Ipv6(ip)) => ip.is_loopback(),
+        None => false,
+    }
+}
+
+/// Listen on the redirect URI's host/port and wait for Spotify to redirect the browser back
+pub async fn wait_for_callback(
+    redirect_uri: &str,
+    request: &mut AuthorizationRequest,
+    wait: Duration,
+) -> Result<String> {
+    let redirect = Url::parse(redirect_uri)
+        .with_context(|| format!("Invalid redirect URI: {}", redirect_uri))?;
+
+    if !is_loopback_redirect(redirect_uri) {
+        return Err(anyhow!("Redirect URI {} is not a local http:// address", redirect_uri));
+    }
+
+    let port = redirect.port_or_known_default().unwrap_or(80);
+    let addr: SocketAddr = match redirect.host() {
+        Some(Host::Ipv4(ip)) => (ip, port).into(),
+        Some(Host::Ipv6(ip)) => (ip, port).into(),
+        _ => ([127, 0, 0, 1], port).into(),
+    };
+
+    let listener = TcpListener::bind(addr).await.map_err(|e| match e.kind() {
+        ErrorKind::AddrInUse => anyhow!(
+            "Port {} is already in use, so the login callback cannot be received on {}. \
+             Close the program using that port or change redirect_uri in config.toml \
+             (and in your Spotify app settings) to a free port.",
+            port,
+            redirect_uri
+        ),
+        _ => anyhow!("Failed to listen for the login callback on {}: {}", addr, e),
+    })?;
+
+    info!("🌐 Waiting for Spotify login callback on http://{}{}", addr, redirect.path());
+
+    timeout(wait, accept_callback(&listener, redirect.path(), request))
+        .await
+        .map_err(|_| anyhow!(
+            "Timed out after {}s waiting for the Spotify login to complete in the browser",
+            wait.as_secs()
+        ))?
+}
+
+/// Accept connections until a redirect for this request hits the callback path
+async fn accept_callback(
+    listener: &TcpListener,
+    callback_path: &str,
+    request: &mut AuthorizationRequest,
+) -> Result<String> {
+    loop {
+        let (mut stream, peer) = listener.accept().await
+            .context("Failed to accept login callback connection")?;
+
+        let target = match read_request_target(&mut stream).await {
+            Ok(target) => target,
+            Err(e) => {
+                warn!("Ignoring malformed request from {}: {}", peer, e);
+                let _ = respond(&mut stream, "400 Bad Request", "Bad Request").await;
+                continue;
+            }
+        };
+
+        // Browsers also ask for /favicon.ico and friends, only the callback path counts
+        let url = match Url::parse("http://localhost").and_then(|base| base.join(&target)) {
+            Ok(url) if url.path() == callback_path => url,
+            _ => {
+                let _ = respond(&mut stream, "404 Not Found", "Not Found").await;
+                continue;
+            }
+        };
+
+        // Redirects for other requests are someone else's, keep waiting for ours
+        let result = request.accept(&parse_callback_params(&url));
+        if let Err(e @ (AuthorizationError::MissingState | AuthorizationError::StateMismatch)) = &result {
+            warn!("Ignoring login callback from {}: {}", peer, e);
+            let _ = respond(&mut stream, "400 Bad Request", "Bad Request").await;
+            continue;
+        }
+
+        let page = if result.is_ok() { SUCCESS_PAGE } else { FAILURE_PAGE };
+        if let Err(e) = respond(&mut stream, "200 OK", page).await {
+            warn!("Failed to send confirmation page to browser: {}", e);
+        }
+
+        return result.map_err(Into::into);
+    }
+}
+
+/// Pull `code`, `state` and the error fields out of a redirect URL
+pub fn parse_callback_params(url: &Url) -> CallbackParams {
+    let find = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    };
+
+    CallbackParams {
+        code: find("code"),
+        state: find("state"),
+        error: find("error"),
+        error_description: find("error_description"),
+    }
+}
+
+/// Read the request head and return the request target (path + query) of a GET
+async fn read_request_target(stream: &mut TcpStream) -> Result<String> {
+    let mut buffer = Vec::with_capacity(1024);
+    let mut chunk = [0u8; 1024];
+
+    while !buffer.windows(4).any(|w| w == b"\r\n\r\n") {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+        if buffer.len() > MAX_REQUEST_BYTES {
+            return Err(anyhow!("Request head too large"));
+        }
+    }
+
+    let head = String::from_utf8_lossy(&buffer);
+    let request_line = head.lines().next().unwrap_or_default();
+    let mut parts = request_line.split_whitespace();
+
+    match (parts.next(), parts.next()) {
+        (Some("GET"), Some(target)) => Ok(target.to_string()),
+        _ => Err(anyhow!("Unexpected request line: {:?}", request_line)),
+    }
+}
+
+async fn respond(stream: &mut TcpStream, status: &str, body: &str) -> Result<()> {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATE: &str = "expected-state";
+
+    fn request() -> AuthorizationRequest {
+        AuthorizationRequest::with_state(STATE.to_string(), AUTHORIZATION_MAX_AGE)
+    }
+
+    fn params(url: &str) -> CallbackParams {
+        parse_callback_params(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn accepts_code_with_matching_state() {
+        let mut request = request();
+        let result = request.accept(&params("http://127.0.0.1:8888/callback?code=abc123&state=expected-state"));
+        assert_eq!(result, Ok("abc123".to_string()));
+    }
+
+    #[test]
+    fn rejects_mismatched_state() {
+        let mut request = request();
+        let result = request.accept(&params("http://127.0.0.1:8888/callback?code=abc123&state=other-state"));
+        assert_eq!(result, Err(AuthorizationError::StateMismatch));
+    }
+
+    #[test]
+    fn rejects_missing_state() {
+        let mut request = request();
+        let result = request.accept(&params("http://127.0.0.1:8888/callback?code=abc123"));
+        assert_eq!(result, Err(AuthorizationError::MissingState));
+    }
+
+    #[test]
+    fn rejects_missing_code() {
+        let mut request = request();
+        let result = request.accept(&params("http://127.0.0.1:8888/callback?state=expected-state"));
+        assert_eq!(result, Err(AuthorizationError::MissingCode));
+    }
+
+    #[test]
+    fn reports_access_denied() {
+        let mut request = request();
+        let result = request.accept(&params("http://127.0.0.1:8888/callback?error=access_denied&state=expected-state"));
+        let error = result.unwrap_err();
+        assert_eq!(error, AuthorizationError::Spotify { error: "access_denied".to_string(), description: None });
+        assert!(error.to_string().contains("declined"));
+    }
+
+    #[test]
+    fn reports_error_description() {
+        let mut request = request();
+        let result = request.accept(&params(
+            "http://127.0.0.1:8888/callback?error=invalid_client&error_description=Invalid+client+id&state=expected-state",
+        ));
+        let message = result.unwrap_err().to_string();
+        assert!(message.contains("Client ID"));
+        assert!(message.contains("Invalid client id"));
+    }
+
+    #[test]
+    fn reports_unknown_error_verbatim() {
+        let mut request = request();
+        let result = request.accept(&params("http://127.0.0.1:8888/callback?error=something_new&state=expected-state"));
+        assert!(result.unwrap_err().to_string().contains("something_new"));
+    }
+
+    #[test]
+    fn error_with_mismatched_state_is_unrelated() {
+        let mut request = request();
+        let result = request.accept(&params("http://127.0.0.1:8888/callback?error=access_denied&state=other-state"));
+        assert_eq!(result, Err(AuthorizationError::StateMismatch));
+    }
+
+    #[test]
+    fn rejects_reused_code() {
+        let mut request = request();
+        let url = "http://127.0.0.1:8888/callback?code=abc123&state=expected-state";
+        assert!(request.accept(&params(url)).is_ok());
+        assert_eq!(request.accept(&params(url)), Err(AuthorizationError::AlreadyRedeemed));
+    }
+
+    #[test]
+    fn rejects_stale_response() {
+        let mut request = AuthorizationRequest::with_state(STATE.to_string(), Duration::from_secs(60));
+        request.issued_at = Instant::now() - Duration::from_secs(61);
+        let result = request.accept(&params("http://127.0.0.1:8888/callback?code=abc123&state=expected-state"));
+        assert!(matches!(result, Err(AuthorizationError::Stale { .. })));
+    }
+
+    async fn get(addr: SocketAddr, target: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", target).as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn callback_keeps_waiting_after_a_mismatched_state() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut request = request();
+
+        let browser = tokio::spawn(async move {
+            let forged = get(addr, "/callback?error=access_denied&state=other-state").await;
+            let real = get(addr, "/callback?code=abc123&state=expected-state").await;
+            (forged, real)
+        });
+        let result = accept_callback(&listener, "/callback", &mut request).await;
+        let (forged, real) = browser.await.unwrap();
+
+        assert_eq!(result.unwrap(), "abc123");
+        assert!(forged.starts_with("HTTP/1.1 400"));
+        assert!(real.starts_with("HTTP/1.1 200"));
+    }
+
+    #[test]
+    fn fresh_requests_use_distinct_states() {
+        assert_ne!(AuthorizationRequest::new().state(), AuthorizationRequest::new().state());
+    }
+}
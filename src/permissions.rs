@@ -0,0 +1,116 @@
+use std::{collections::HashSet, fmt};
+
+/// Everything the app does against the Web API, used to look up required OAuth scopes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    ReadCurrentTrack,
+    LikeTrack,
+    UnlikeTrack,
+    CheckLiked,
+    SyncLibrary,
+    ControlPlayback,
+    ListDevices,
+    ReadProfile,
+}
+
+impl Action {
+    /// Every registered action, used to build the scope list for a fresh login
+    pub const ALL: &'static [Action] = &[
+        Action::ReadCurrentTrack,
+        Action::LikeTrack,
+        Action::UnlikeTrack,
+        Action::CheckLiked,
+        Action::SyncLibrary,
+        Action::ControlPlayback,
+        Action::ListDevices,
+        Action::ReadProfile,
+    ];
+
+    /// OAuth scopes Spotify requires for this action
+    pub fn required_scopes(self) -> &'static [&'static str] {
+        match self {
+            Action::ReadCurrentTrack => &["user-read-currently-playing", "user-read-playback-state"],
+            // Likes are verified by reading the library back
+            Action::LikeTrack | Action::UnlikeTrack => &["user-library-modify", "user-library-read"],
+            Action::CheckLiked | Action::SyncLibrary => &["user-library-read"],
+            // Toggling play/pause reads the playback state first
+            Action::ControlPlayback => &["user-modify-playback-state", "user-read-playback-state"],
+            Action::ListDevices => &["user-read-playback-state"],
+            Action::ReadProfile => &["user-read-private"],
+        }
+    }
+
+    /// Required scopes that are not in `granted`
+    pub fn missing_scopes(self, granted: &HashSet<String>) -> Vec<&'static str> {
+        self.required_scopes()
+            .iter()
+            .copied()
+            .filter(|scope| !granted.contains(*scope))
+            .collect()
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Action::ReadCurrentTrack => "read the current track",
+            Action::LikeTrack => "like tracks",
+            Action::UnlikeTrack => "remove liked tracks",
+            Action::CheckLiked => "check liked tracks",
+            Action::SyncLibrary => "sync your Liked Songs",
+            Action::ControlPlayback => "control playback",
+            Action::ListDevices => "list your Spotify devices",
+            Action::ReadProfile => "read your profile",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Union of the scopes of all registered actions
+pub fn all_scopes() -> HashSet<String> {
+    Action::ALL
+        .iter()
+        .flat_map(|action| action.required_scopes())
+        .map(|scope| scope.to_string())
+        .collect()
+}
+
+/// The granted token lacks scopes an action needs and re-consent did not happen
+#[derive(Debug, Clone)]
+pub struct MissingPermission {
+    pub action: Action,
+    pub missing: Vec<&'static str>,
+}
+
+impl fmt::Display for MissingPermission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Missing Spotify permission to {} ({}). Re-authorize the app to grant it.",
+            self.action,
+            self.missing.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for MissingPermission {}
+
+/// A user action lacks scopes the user hasn't been asked for yet, see `SpotifyManager::with_consent`
+#[derive(Debug, Clone)]
+pub struct ConsentNeeded {
+    pub action: Action,
+    pub missing: Vec<&'static str>,
+}
+
+impl fmt::Display for ConsentNeeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Spotify permission to {} has to be granted first ({})",
+            self.action,
+            self.missing.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for ConsentNeeded {}
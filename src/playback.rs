@@ -0,0 +1,201 @@
+use rspotify::{
+    http::HttpError,
+    model::{CurrentPlaybackContext, RepeatState},
+    ClientError,
+};
+use std::{fmt, sync::Mutex, time::Duration};
+
+/// Volume an unmute goes to when the volume from before muting isn't known
+pub const UNMUTE_FALLBACK_VOLUME: u8 = 50;
+
+/// How long volume presses are collected before the first API call
+pub const VOLUME_COALESCE_WINDOW: Duration = Duration::from_millis(150);
+
+/// Spotify has no device to carry out a player command
+///
+/// Player endpoints answer 404 when no Spotify app is open and active.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoActiveDevice;
+
+impl fmt::Display for NoActiveDevice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("No active Spotify device - start playing on one of your devices and try again")
+    }
+}
+
+impl std::error::Error for NoActiveDevice {}
+
+/// Replace the 404 of a failed player command with `NoActiveDevice`, leave other errors alone
+pub fn explain_no_device(error: anyhow::Error) -> anyhow::Error {
+    let not_found = error.chain().any(|cause| match cause.downcast_ref::<ClientError>() {
+        Some(ClientError::Http(http)) => matches!(http.as_ref(), HttpError::StatusCode(response) if response.status().as_u16() == 404),
+        _ => false,
+    });
+
+    if not_found {
+        anyhow::Error::from(NoActiveDevice)
+    } else {
+        error
+    }
+}
+
+/// `volume` moved by `delta` percentage points, kept within 0-100
+pub fn step_volume(volume: u8, delta: i32) -> u8 {
+    (i32::from(volume) + delta).clamp(0, 100) as u8
+}
+
+/// Shuffle and repeat mode of the active device
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlayerModes {
+    pub shuffle: bool,
+    pub repeat: RepeatState,
+}
+
+impl Default for PlayerModes {
+    fn default() -> Self {
+        Self { shuffle: false, repeat: RepeatState::Off }
+    }
+}
+
+impl From<&CurrentPlaybackContext> for PlayerModes {
+    fn from(playback: &CurrentPlaybackContext) -> Self {
+        Self { shuffle: playback.shuffle_state, repeat: playback.repeat_state }
+    }
+}
+
+/// Repeat mode after `repeat` in the off → context → track cycle
+pub fn next_repeat(repeat: RepeatState) -> RepeatState {
+    match repeat {
+        RepeatState::Off => RepeatState::Context,
+        RepeatState::Context => RepeatState::Track,
+        RepeatState::Track => RepeatState::Off,
+    }
+}
+
+/// Tray and notification text for a repeat mode
+pub fn repeat_label(repeat: RepeatState) -> &'static str {
+    match repeat {
+        RepeatState::Off => "🔁 Repeat: Off",
+        RepeatState::Context => "🔁 Repeat: All",
+        RepeatState::Track => "🔂 Repeat: Track",
+    }
+}
+
+/// Where playback is within the playing track or episode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeekPosition {
+    pub position_ms: u32,
+    pub duration_ms: u32,
+}
+
+impl SeekPosition {
+    /// Position moved by `offset_ms` (negative seeks back), kept within the item
+    pub fn offset_by(self, offset_ms: i64) -> Self {
+        let position_ms = (i64::from(self.position_ms) + offset_ms).clamp(0, i64::from(self.duration_ms)) as u32;
+        Self { position_ms, ..self }
+    }
+}
+
+impl fmt::Display for SeekPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} / {}", minutes_seconds(self.position_ms), minutes_seconds(self.duration_ms))
+    }
+}
+
+fn minutes_seconds(ms: u32) -> String {
+    let seconds = ms / 1000;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Adds up volume presses so a burst of them turns into a single API call
+///
+/// The first press starts an apply task (`push` returns true); presses arriving
+/// while that task waits or talks to Spotify are added to the pending change,
+/// which the task picks up with `take` until nothing is left.
+#[derive(Debug, Default)]
+pub struct VolumeCoalescer {
+    state: Mutex<CoalescerState>,
+}
+
+#[derive(Debug, Default)]
+struct CoalescerState {
+    pending: i32,
+    applying: bool,
+}
+
+impl VolumeCoalescer {
+    /// Add a press, returns whether the caller has to start an apply task
+    pub fn push(&self, delta: i32) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.pending += delta;
+        !std::mem::replace(&mut state.applying, true)
+    }
+
+    /// Take the change pressed so far, `None` (ending the apply task) once it adds up to nothing
+    pub fn take(&self) -> Option<i32> {
+        let mut state = self.state.lock().unwrap();
+        let pending = std::mem::take(&mut state.pending);
+        if pending == 0 {
+            state.applying = false;
+            return None;
+        }
+        Some(pending)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn volume_steps_stay_within_range() {
+        assert_eq!(step_volume(50, 10), 60);
+        assert_eq!(step_volume(95, 10), 100);
+        assert_eq!(step_volume(5, -10), 0);
+    }
+
+    #[test]
+    fn repeat_cycles_through_context_and_track() {
+        assert_eq!(next_repeat(RepeatState::Off), RepeatState::Context);
+        assert_eq!(next_repeat(RepeatState::Context), RepeatState::Track);
+        assert_eq!(next_repeat(RepeatState::Track), RepeatState::Off);
+    }
+
+    #[test]
+    fn seeks_stay_within_the_item() {
+        let position = SeekPosition { position_ms: 170_000, duration_ms: 180_000 };
+
+        assert_eq!(position.offset_by(15_000).position_ms, 180_000);
+        assert_eq!(position.offset_by(-15_000).position_ms, 155_000);
+        assert_eq!(position.offset_by(-200_000).position_ms, 0);
+        assert_eq!(position.offset_by(-15_000).to_string(), "2:35 / 3:00");
+    }
+
+    #[test]
+    fn presses_during_a_change_are_applied_together() {
+        let coalescer = VolumeCoalescer::default();
+
+        assert!(coalescer.push(10));
+        assert!(!coalescer.push(10));
+        assert!(!coalescer.push(-5));
+        assert_eq!(coalescer.take(), Some(15));
+
+        // A press while the first change is in flight is picked up by the same task
+        assert!(!coalescer.push(10));
+        assert_eq!(coalescer.take(), Some(10));
+        assert_eq!(coalescer.take(), None);
+
+        // Once the task ended, the next press starts a new one
+        assert!(coalescer.push(-10));
+    }
+
+    #[test]
+    fn presses_that_cancel_out_make_no_call() {
+        let coalescer = VolumeCoalescer::default();
+
+        assert!(coalescer.push(10));
+        assert!(!coalescer.push(-10));
+
+        assert_eq!(coalescer.take(), None);
+    }
+}
@@ -0,0 +1,188 @@
+use rspotify::{http::HttpError, ClientError};
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tokio::time::sleep;
+use tracing::info;
+
+/// Longest rate limit a user action waits out instead of failing
+pub const MAX_USER_WAIT: Duration = Duration::from_secs(10);
+
+/// Used when a 429 comes without a usable Retry-After header
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// Who an API call is made for, decides how it behaves while rate limited
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    /// Triggered by a hotkey or the tray, worth waiting a little for
+    User,
+    /// The track poller, skipped while limited or while user actions are waiting
+    Background,
+}
+
+/// Shared 429 state for every Spotify call the app makes
+///
+/// Spotify rate limits per app, so one limiter is shared by the manager, the
+/// poller and the tray handlers, and carried over when switching profiles.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    limited_until: Mutex<Option<Instant>>,
+    pending_user_actions: AtomicUsize,
+}
+
+impl RateLimiter {
+    /// Record a 429, extending (never shortening) the current limit
+    pub fn limit_for(&self, retry_after: Duration) {
+        let until = Instant::now() + retry_after;
+        let mut limited_until = self.limited_until.lock().unwrap();
+        if limited_until.is_none_or(|current| current < until) {
+            *limited_until = Some(until);
+        }
+    }
+
+    /// Time left until requests may be sent again, `None` if not limited
+    pub fn remaining(&self) -> Option<Duration> {
+        self.limited_until.lock().unwrap()
+            .and_then(|until| until.checked_duration_since(Instant::now()))
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    pub fn is_limited(&self) -> bool {
+        self.remaining().is_some()
+    }
+
+    /// Wait until a request may be sent; only short limits are waited out, and only for user actions
+    pub async fn acquire(&self, priority: Priority) -> Result<(), RateLimited> {
+        match self.remaining() {
+            None => Ok(()),
+            Some(remaining) if priority == Priority::User && remaining <= MAX_USER_WAIT => {
+                info!("⏳ Rate limited, waiting {}ms before sending the request", remaining.as_millis());
+                sleep(remaining).await;
+                Ok(())
+            }
+            Some(remaining) => Err(RateLimited { retry_after: remaining }),
+        }
+    }
+
+    /// Mark a user action as waiting for the manager, the poller backs off until it is dropped
+    pub fn user_action(self: &Arc<Self>) -> UserAction {
+        self.pending_user_actions.fetch_add(1, Ordering::SeqCst);
+        UserAction(Arc::clone(self))
+    }
+
+    pub fn user_actions_pending(&self) -> bool {
+        self.pending_user_actions.load(Ordering::SeqCst) > 0
+    }
+
+    /// Retry-After of a 429 response, `None` for any other error
+    pub fn retry_after(error: &ClientError) -> Option<Duration> {
+        let ClientError::Http(http) = error else {
+            return None;
+        };
+        let HttpError::StatusCode(response) = http.as_ref() else {
+            return None;
+        };
+        if response.status().as_u16() != 429 {
+            return None;
+        }
+
+        let retry_after = response.headers()
+            .get("retry-after")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_RETRY_AFTER);
+
+        Some(retry_after)
+    }
+}
+
+/// Guard for a pending user action, see `RateLimiter::user_action`
+pub struct UserAction(Arc<RateLimiter>);
+
+impl Drop for UserAction {
+    fn drop(&mut self) {
+        self.0.pending_user_actions.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Spotify answered 429 and the limit outlasts what this call is willing to wait
+#[derive(Debug, Clone)]
+pub struct RateLimited {
+    pub retry_after: Duration,
+}
+
+impl fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Spotify rate limit reached, try again in {}s",
+            self.retry_after.as_secs_f64().ceil() as u64
+        )
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limit_is_only_ever_extended() {
+        let limiter = RateLimiter::default();
+
+        limiter.limit_for(Duration::from_secs(30));
+        limiter.limit_for(Duration::from_secs(1));
+
+        assert!(limiter.remaining().unwrap() > Duration::from_secs(20));
+    }
+
+    #[tokio::test]
+    async fn background_calls_do_not_wait() {
+        let limiter = RateLimiter::default();
+        limiter.limit_for(Duration::from_secs(5));
+
+        let error = limiter.acquire(Priority::Background).await.unwrap_err();
+
+        assert!(error.retry_after <= Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn user_calls_wait_out_short_limits() {
+        let limiter = RateLimiter::default();
+        limiter.limit_for(Duration::from_millis(50));
+
+        let start = Instant::now();
+        limiter.acquire(Priority::User).await.unwrap();
+
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        assert!(!limiter.is_limited());
+    }
+
+    #[tokio::test]
+    async fn user_calls_fail_on_long_limits() {
+        let limiter = RateLimiter::default();
+        limiter.limit_for(MAX_USER_WAIT * 2);
+
+        assert!(limiter.acquire(Priority::User).await.is_err());
+    }
+
+    #[test]
+    fn user_action_guard_tracks_pending_actions() {
+        let limiter = Arc::new(RateLimiter::default());
+
+        let first = limiter.user_action();
+        let second = limiter.user_action();
+        drop(first);
+        assert!(limiter.user_actions_pending());
+
+        drop(second);
+        assert!(!limiter.user_actions_pending());
+    }
+}
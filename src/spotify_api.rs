@@ -0,0 +1,479 @@
+use rspotify::{
+    model::{CurrentPlaybackContext, CurrentlyPlayingContext, Device, Page, PrivateUser, RepeatState, SavedTrack, TrackId},
+    ClientResult, Token,
+};
+use std::{collections::HashSet, future::Future};
+
+use crate::convergence::Step;
+
+/// The Spotify Web API calls SpotifyManager relies on
+///
+/// `AuthClient` implements this on top of rspotify; tests use the in-memory
+/// `FakeSpotifyApi` so the like/verify logic can run without the network.
+pub trait SpotifyApi: Send + Sync {
+    /// Currently playing context, `None` if nothing is playing
+    fn current_playing(&self) -> impl Future<Output = ClientResult<Option<CurrentlyPlayingContext>>> + Send;
+
+    /// Add tracks to the user's Liked Songs
+    fn saved_tracks_add(&self, track_ids: &[TrackId<'_>]) -> impl Future<Output = ClientResult<()>> + Send;
+
+    /// Remove tracks from the user's Liked Songs
+    fn saved_tracks_delete(&self, track_ids: &[TrackId<'_>]) -> impl Future<Output = ClientResult<()>> + Send;
+
+    /// Whether each track is in the user's Liked Songs, in request order
+    fn saved_tracks_contains(&self, track_ids: &[TrackId<'_>]) -> impl Future<Output = ClientResult<Vec<bool>>> + Send;
+
+    /// One page of the user's Liked Songs, most recently added first
+    fn saved_tracks(&self, limit: u32, offset: u32) -> impl Future<Output = ClientResult<Page<SavedTrack>>> + Send;
+
+    /// Playback state including the active device, `None` if there is none
+    fn current_playback(&self) -> impl Future<Output = ClientResult<Option<CurrentPlaybackContext>>> + Send;
+
+    /// Set the active device's volume
+    fn set_volume(&self, volume_percent: u8) -> impl Future<Output = ClientResult<()>> + Send;
+
+    /// Pause playback on the active device
+    fn pause_playback(&self) -> impl Future<Output = ClientResult<()>> + Send;
+
+    /// Resume playback on the active device
+    fn resume_playback(&self) -> impl Future<Output = ClientResult<()>> + Send;
+
+    /// Skip to the next track
+    fn next_track(&self) -> impl Future<Output = ClientResult<()>> + Send;
+
+    /// Skip to the previous track
+    fn previous_track(&self) -> impl Future<Output = ClientResult<()>> + Send;
+
+    /// Jump to a position within the playing item
+    fn seek_track(&self, position_ms: u32) -> impl Future<Output = ClientResult<()>> + Send;
+
+    /// Turn shuffle on or off
+    fn set_shuffle(&self, shuffle: bool) -> impl Future<Output = ClientResult<()>> + Send;
+
+    /// Set the repeat mode
+    fn set_repeat(&self, repeat: RepeatState) -> impl Future<Output = ClientResult<()>> + Send;
+
+    /// Spotify Connect devices available to the user
+    fn devices(&self) -> impl Future<Output = ClientResult<Vec<Device>>> + Send;
+
+    /// Move playback to another device, keeping it playing or paused
+    fn transfer_playback(&self, device_id: &str) -> impl Future<Output = ClientResult<()>> + Send;
+
+    fn current_user(&self) -> impl Future<Output = ClientResult<PrivateUser>> + Send;
+
+    /// Refresh the access token
+    fn refresh_token(&self) -> impl Future<Output = ClientResult<()>> + Send;
+
+    /// Snapshot of the current token, `None` before authorization
+    fn token(&self) -> impl Future<Output = Option<Token>> + Send;
+
+    /// Ask the user to grant `scopes` in addition to the ones already requested
+    ///
+    /// The login doesn't borrow the client, so it can wait for the browser without
+    /// the manager locked. Its token only takes effect once passed to `set_token`.
+    fn reauthorize(&self, scopes: HashSet<String>) -> Step<'static, Token>;
+
+    /// Use `token` for every following call
+    fn set_token(&self, token: Token) -> impl Future<Output = ()> + Send;
+}
+
+#[cfg(test)]
+pub mod fake {
+    use super::*;
+    use chrono::{DateTime, Duration as ChronoDuration, Utc};
+    use rspotify::{prelude::*, ClientError};
+    use serde_json::{json, Value};
+    use std::{collections::HashMap, sync::Mutex};
+
+    use crate::{mock_spotify, permissions, playback::PlayerModes};
+
+    /// In-memory Spotify with knobs for the eventual consistency the verifier deals with
+    #[derive(Default)]
+    pub struct FakeSpotifyApi {
+        state: Mutex<FakeState>,
+    }
+
+    #[derive(Default)]
+    struct FakeState {
+        /// Currently playing payload, with `is_playing` updated by player commands
+        playing: Option<Value>,
+        /// Volume the active device reports, `None` if it can't be controlled
+        volume: Option<u32>,
+        modes: PlayerModes,
+        /// Liked Songs with their added_at
+        liked: HashMap<String, DateTime<Utc>>,
+        /// Library as reads last saw it, served while they lag behind the writes
+        stale_liked: HashMap<String, DateTime<Utc>>,
+        lagging_reads: u32,
+        /// Reads that keep returning the pre-write state after every write
+        read_lag: u32,
+        /// Writes that are acknowledged but never applied
+        dropped_writes: u32,
+        /// Tracks whose writes are acknowledged but never applied
+        stuck_tracks: HashSet<String>,
+        /// Most IDs sent in one saved-tracks request
+        largest_request: usize,
+        failing_reads: u32,
+        add_calls: u32,
+        delete_calls: u32,
+        contains_calls: u32,
+        saved_tracks_calls: u32,
+        /// Player commands in the order they were sent, e.g. "pause"
+        player_commands: Vec<&'static str>,
+        /// Devices by ID and name, besides "fake-device" which plays `playing`
+        devices: Vec<(String, String)>,
+        /// Device playback was transferred to
+        active_device: Option<String>,
+        /// Scopes the token carries, all of them if `None`
+        scopes: Option<HashSet<String>>,
+        reauthorizations: u32,
+    }
+
+    impl FakeState {
+        fn active_device_id(&self) -> Option<&str> {
+            self.active_device.as_deref().or(self.playing.as_ref().map(|_| "fake-device"))
+        }
+
+        fn device_list(&self) -> Vec<Value> {
+            let playing_device = self.playing.as_ref().map(|_| ("fake-device".to_string(), "Fake Device".to_string()));
+            playing_device.iter()
+                .chain(&self.devices)
+                .map(|(id, name)| {
+                    let is_active = self.active_device_id() == Some(id.as_str());
+                    mock_spotify::device_json(id, name, is_active, self.volume)
+                })
+                .collect()
+        }
+    }
+
+    impl FakeSpotifyApi {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Start playing a track
+        pub fn playing(self, id: &str, name: &str, artist: &str) -> Self {
+            self.state.lock().unwrap().playing = Some(mock_spotify::currently_playing_json(id, name, artist));
+            self
+        }
+
+        /// Pause whatever is playing
+        pub fn paused(self) -> Self {
+            if let Some(playing) = self.state.lock().unwrap().playing.as_mut() {
+                playing["is_playing"] = json!(false);
+            }
+            self
+        }
+
+        /// Another Spotify Connect device the user could play on
+        pub fn with_device(self, id: &str, name: &str) -> Self {
+            self.state.lock().unwrap().devices.push((id.to_string(), name.to_string()));
+            self
+        }
+
+        pub fn active_device(&self) -> Option<String> {
+            self.state.lock().unwrap().active_device_id().map(str::to_string)
+        }
+
+        /// Move the playing item to `progress_ms`
+        pub fn at_progress(self, progress_ms: u32) -> Self {
+            if let Some(playing) = self.state.lock().unwrap().playing.as_mut() {
+                playing["progress_ms"] = json!(progress_ms);
+            }
+            self
+        }
+
+        pub fn progress_ms(&self) -> Option<u64> {
+            self.state.lock().unwrap().playing.as_ref().and_then(|playing| playing["progress_ms"].as_u64())
+        }
+
+        /// Volume the active device reports
+        pub fn with_volume(self, volume: u32) -> Self {
+            self.state.lock().unwrap().volume = Some(volume);
+            self
+        }
+
+        pub fn is_playing(&self) -> bool {
+            self.state.lock().unwrap().playing.as_ref().is_some_and(|playing| playing["is_playing"] == json!(true))
+        }
+
+        pub fn volume(&self) -> Option<u32> {
+            self.state.lock().unwrap().volume
+        }
+
+        pub fn player_commands(&self) -> Vec<&'static str> {
+            self.state.lock().unwrap().player_commands.clone()
+        }
+
+        /// Put a track in Liked Songs up front
+        pub fn with_liked(self, id: &str) -> Self {
+            self.state.lock().unwrap().liked.insert(id.to_string(), Utc::now());
+            self
+        }
+
+        /// Like a track as if from another Spotify client at `added_at`
+        pub fn like_at(&self, id: &str, added_at: DateTime<Utc>) {
+            self.state.lock().unwrap().liked.insert(id.to_string(), added_at);
+        }
+
+        /// Unlike a track as if from another Spotify client
+        pub fn unlike(&self, id: &str) {
+            self.state.lock().unwrap().liked.remove(id);
+        }
+
+        /// Keep answering `contains` with the old state for `reads` calls after each write
+        pub fn with_read_lag(self, reads: u32) -> Self {
+            self.state.lock().unwrap().read_lag = reads;
+            self
+        }
+
+        /// Acknowledge the next `writes` add/delete calls without applying them
+        pub fn dropping_writes(self, writes: u32) -> Self {
+            self.state.lock().unwrap().dropped_writes = writes;
+            self
+        }
+
+        /// Acknowledge every add/delete of `id` without applying it
+        pub fn ignoring_writes_for(self, id: &str) -> Self {
+            self.state.lock().unwrap().stuck_tracks.insert(id.to_string());
+            self
+        }
+
+        /// Fail the next `reads` `contains` calls
+        pub fn failing_reads(self, reads: u32) -> Self {
+            self.state.lock().unwrap().failing_reads = reads;
+            self
+        }
+
+        /// Hand out a token with only `scopes`, until a re-authorization grants more
+        pub fn with_scopes(self, scopes: &[String]) -> Self {
+            self.state.lock().unwrap().scopes = Some(scopes.iter().cloned().collect());
+            self
+        }
+
+        pub fn reauthorizations(&self) -> u32 {
+            self.state.lock().unwrap().reauthorizations
+        }
+
+        pub fn is_liked(&self, id: &str) -> bool {
+            self.state.lock().unwrap().liked.contains_key(id)
+        }
+
+        pub fn add_calls(&self) -> u32 {
+            self.state.lock().unwrap().add_calls
+        }
+
+        pub fn delete_calls(&self) -> u32 {
+            self.state.lock().unwrap().delete_calls
+        }
+
+        pub fn contains_calls(&self) -> u32 {
+            self.state.lock().unwrap().contains_calls
+        }
+
+        pub fn saved_tracks_calls(&self) -> u32 {
+            self.state.lock().unwrap().saved_tracks_calls
+        }
+
+        pub fn largest_request(&self) -> usize {
+            self.state.lock().unwrap().largest_request
+        }
+
+        fn player_command(&self, command: &'static str) {
+            let mut state = self.state.lock().unwrap();
+            state.player_commands.push(command);
+            let is_playing = match command {
+                "pause" => false,
+                "play" => true,
+                _ => return,
+            };
+            if let Some(playing) = state.playing.as_mut() {
+                playing["is_playing"] = json!(is_playing);
+            }
+        }
+
+        fn write(&self, track_ids: &[TrackId<'_>], like: bool) {
+            let mut state = self.state.lock().unwrap();
+            if like {
+                state.add_calls += 1;
+            } else {
+                state.delete_calls += 1;
+            }
+            state.largest_request = state.largest_request.max(track_ids.len());
+
+            if state.dropped_writes > 0 {
+                state.dropped_writes -= 1;
+                return;
+            }
+
+            // Reads keep serving the state from before the first write they haven't caught up with
+            if state.lagging_reads == 0 {
+                state.stale_liked = state.liked.clone();
+            }
+            state.lagging_reads = state.read_lag;
+            for id in track_ids {
+                if state.stuck_tracks.contains(id.id()) {
+                    continue;
+                }
+                if like {
+                    state.liked.insert(id.id().to_string(), Utc::now());
+                } else {
+                    state.liked.remove(id.id());
+                }
+            }
+        }
+    }
+
+    impl SpotifyApi for FakeSpotifyApi {
+        async fn current_playing(&self) -> ClientResult<Option<CurrentlyPlayingContext>> {
+            match self.state.lock().unwrap().playing.clone() {
+                Some(playing) => Ok(Some(serde_json::from_value(playing)?)),
+                None => Ok(None),
+            }
+        }
+
+        async fn current_playback(&self) -> ClientResult<Option<CurrentPlaybackContext>> {
+            let state = self.state.lock().unwrap();
+            let Some(playing) = &state.playing else {
+                return Ok(None);
+            };
+            let device = state.device_list()
+                .into_iter()
+                .find(|device| device["is_active"] == json!(true))
+                .unwrap_or(Value::Null);
+            Ok(Some(serde_json::from_value(mock_spotify::playback_json(playing, device, state.modes))?))
+        }
+
+        async fn set_volume(&self, volume_percent: u8) -> ClientResult<()> {
+            let mut state = self.state.lock().unwrap();
+            state.player_commands.push("volume");
+            state.volume = Some(u32::from(volume_percent));
+            Ok(())
+        }
+
+        async fn saved_tracks_add(&self, track_ids: &[TrackId<'_>]) -> ClientResult<()> {
+            self.write(track_ids, true);
+            Ok(())
+        }
+
+        async fn saved_tracks_delete(&self, track_ids: &[TrackId<'_>]) -> ClientResult<()> {
+            self.write(track_ids, false);
+            Ok(())
+        }
+
+        async fn saved_tracks_contains(&self, track_ids: &[TrackId<'_>]) -> ClientResult<Vec<bool>> {
+            let mut state = self.state.lock().unwrap();
+            state.contains_calls += 1;
+            state.largest_request = state.largest_request.max(track_ids.len());
+
+            if state.failing_reads > 0 {
+                state.failing_reads -= 1;
+                return Err(ClientError::Io(std::io::Error::other("simulated network failure")));
+            }
+
+            let library = if state.lagging_reads > 0 {
+                state.lagging_reads -= 1;
+                &state.stale_liked
+            } else {
+                &state.liked
+            };
+
+            Ok(track_ids.iter().map(|id| library.contains_key(id.id())).collect())
+        }
+
+        async fn saved_tracks(&self, limit: u32, offset: u32) -> ClientResult<Page<SavedTrack>> {
+            let mut state = self.state.lock().unwrap();
+            state.saved_tracks_calls += 1;
+            let mut saved: Vec<(String, DateTime<Utc>)> = state.liked.iter()
+                .map(|(id, added_at)| (id.clone(), *added_at))
+                .collect();
+            saved.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+            let page = mock_spotify::saved_tracks_page_json(&saved, limit as usize, offset as usize);
+            Ok(serde_json::from_value(page)?)
+        }
+
+        async fn pause_playback(&self) -> ClientResult<()> {
+            self.player_command("pause");
+            Ok(())
+        }
+
+        async fn resume_playback(&self) -> ClientResult<()> {
+            self.player_command("play");
+            Ok(())
+        }
+
+        async fn next_track(&self) -> ClientResult<()> {
+            self.player_command("next");
+            Ok(())
+        }
+
+        async fn previous_track(&self) -> ClientResult<()> {
+            self.player_command("previous");
+            Ok(())
+        }
+
+        async fn seek_track(&self, position_ms: u32) -> ClientResult<()> {
+            let mut state = self.state.lock().unwrap();
+            state.player_commands.push("seek");
+            if let Some(playing) = state.playing.as_mut() {
+                playing["progress_ms"] = json!(position_ms);
+            }
+            Ok(())
+        }
+
+        async fn set_shuffle(&self, shuffle: bool) -> ClientResult<()> {
+            let mut state = self.state.lock().unwrap();
+            state.player_commands.push("shuffle");
+            state.modes.shuffle = shuffle;
+            Ok(())
+        }
+
+        async fn set_repeat(&self, repeat: RepeatState) -> ClientResult<()> {
+            let mut state = self.state.lock().unwrap();
+            state.player_commands.push("repeat");
+            state.modes.repeat = repeat;
+            Ok(())
+        }
+
+        async fn devices(&self) -> ClientResult<Vec<Device>> {
+            let devices = self.state.lock().unwrap().device_list();
+            Ok(serde_json::from_value(Value::Array(devices))?)
+        }
+
+        async fn transfer_playback(&self, device_id: &str) -> ClientResult<()> {
+            let mut state = self.state.lock().unwrap();
+            state.player_commands.push("transfer");
+            state.active_device = Some(device_id.to_string());
+            Ok(())
+        }
+
+        async fn current_user(&self) -> ClientResult<PrivateUser> {
+            Ok(serde_json::from_value(mock_spotify::user_json())?)
+        }
+
+        async fn refresh_token(&self) -> ClientResult<()> {
+            Ok(())
+        }
+
+        async fn token(&self) -> Option<Token> {
+            Some(Token {
+                access_token: "fake".to_string(),
+                expires_at: Some(Utc::now() + ChronoDuration::hours(1)),
+                refresh_token: Some("fake".to_string()),
+                scopes: self.state.lock().unwrap().scopes.clone().unwrap_or_else(permissions::all_scopes),
+                ..Default::default()
+            })
+        }
+
+        fn reauthorize(&self, scopes: HashSet<String>) -> Step<'static, Token> {
+            self.state.lock().unwrap().reauthorizations += 1;
+            let token = Token { scopes, ..Default::default() };
+            Box::pin(async move { Ok(token) })
+        }
+
+        async fn set_token(&self, token: Token) {
+            self.state.lock().unwrap().scopes = Some(token.scopes);
+        }
+    }
+}
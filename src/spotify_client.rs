@@ -1,15 +1,74 @@
 use anyhow::{anyhow, Context, Result};
 use rspotify::{
-    model::{CurrentlyPlayingContext, PlayableItem, TrackId},
+    model::{CurrentPlaybackContext, Device, Page, PlayableItem, SavedTrack, TrackId},
     prelude::*,
-    scopes, AuthCodeSpotify, Config, Credentials, OAuth,
+    Config, OAuth,
 };
-use std::{fs, path::PathBuf, time::Duration};
-use tokio::time::sleep;
+use notify_rust::Notification;
+use std::{collections::{HashMap, HashSet}, slice, sync::Arc, time::{Duration, Instant}};
+use tokio::sync::Mutex;
 use tracing::{info, warn, error};
-use url::Url;
 
-use crate::config::AppConfig;
+use crate::auth_client::AuthClient;
+use crate::action_queue::{ActionQueue, PendingAction, QueuedAction};
+use crate::config::{AppConfig, VerificationConfig};
+use crate::convergence::{self, Step, Superseded, VerificationResult};
+use crate::library::{Library, LibraryTrack};
+use crate::liked_cache::LikedCache;
+use crate::permissions::{self, Action, ConsentNeeded, MissingPermission};
+use crate::playback::{self, NoActiveDevice, PlayerModes, SeekPosition};
+use crate::rate_limit::{Priority, RateLimited, RateLimiter};
+use crate::spotify_api::SpotifyApi;
+use crate::stats::Stats;
+use crate::token_lifecycle::TokenLifecycle;
+use crate::token_store;
+
+/// Most track IDs Spotify accepts in one saved-tracks request
+const BATCH_SIZE: usize = 50;
+
+/// How often the tray poller reads the playing track
+pub const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// An offline like or unlike is only queued for a track seen this recently, the one playing may have changed since
+const LAST_TRACK_MAX_AGE: Duration = Duration::from_secs(3 * POLL_INTERVAL.as_secs());
+
+/// Run an API call with a fresh token, refreshing and retrying once on a 401
+///
+/// Goes through the shared rate limiter: a 429 records its Retry-After, user
+/// calls wait out a short limit and retry once, background calls give up.
+macro_rules! authorized {
+    ($self:ident, $client:ident => $call:expr) => {{
+        $self.rate_limiter.acquire($self.priority).await?;
+        $self.token_lifecycle.ensure_fresh(&$self.client).await?;
+        let mut result = {
+            let $client = &$self.client;
+            $call
+        };
+        if matches!(&result, Err(e) if TokenLifecycle::is_unauthorized(e)) {
+            $self.token_lifecycle.refresh_after_unauthorized(&$self.client).await?;
+            result = {
+                let $client = &$self.client;
+                $call
+            };
+        }
+        if let Some(retry_after) = result.as_ref().err().and_then(RateLimiter::retry_after) {
+            warn!("⏳ Spotify rate limit hit (429), Retry-After {}s", retry_after.as_secs());
+            $self.rate_limiter.limit_for(retry_after);
+            $self.rate_limiter.acquire($self.priority).await?;
+            result = {
+                let $client = &$self.client;
+                $call
+            };
+        }
+        result.map_err(|e| match RateLimiter::retry_after(&e) {
+            Some(retry_after) => {
+                $self.rate_limiter.limit_for(retry_after);
+                anyhow::Error::from(RateLimited { retry_after })
+            }
+            None => anyhow::Error::from(e),
+        })
+    }};
+}
 
 #[derive(Debug, Clone)]
 pub struct TrackInfo {
@@ -19,623 +78,1778 @@ pub struct TrackInfo {
     pub uri: Option<String>,
 }
 
+/// What one background poll of the playback state found, see `SpotifyManager::poll_current_track`
 #[derive(Debug, Clone)]
-pub struct VerificationResult {
-    pub success: bool,
+pub struct PolledPlayback {
+    /// `None` if no track is playing
+    pub track: Option<TrackInfo>,
+    /// `None` without an active device
+    pub modes: Option<PlayerModes>,
+}
+
+impl TrackInfo {
+    /// The track in a playback payload, `None` for anything else (e.g. a podcast episode)
+    fn from_item(item: Option<&PlayableItem>) -> Option<Self> {
+        let Some(PlayableItem::Track(track)) = item else {
+            return None;
+        };
+        
+        Some(Self {
+            id: track.id.as_ref().map(|id| id.to_string()),
+            name: track.name.clone(),
+            artist: track.artists.first()
+                .map(|a| a.name.clone())
+                .unwrap_or_else(|| "Unknown Artist".to_string()),
+            uri: track.id.as_ref().map(|id| format!("spotify:track:{}", id.id())),
+        })
+    }
+    
+    /// Track known only by its ID, e.g. from a batch like; the ID stands in for the name
+    fn from_track_id(track_id: &TrackId<'_>) -> Self {
+        Self {
+            id: Some(track_id.uri()),
+            name: track_id.id().to_string(),
+            artist: "Unknown Artist".to_string(),
+            uri: Some(track_id.uri()),
+        }
+    }
+}
+
+/// A like or unlike Spotify accepted that hasn't been read back yet
+pub struct PendingVerification {
     pub track_info: TrackInfo,
-    pub verified_after_ms: u64,
-    pub attempts: u32,
+    track_id: TrackId<'static>,
+    /// The state verification waits for
+    pub liked: bool,
+    /// Identifies the manager (and so the profile) the write went through
+    liked_cache: Arc<LikedCache>,
+    /// Tells this write apart from later likes or unlikes of the same track
+    generation: u64,
+}
+
+/// How far `SpotifyManager::verify_writes` got, shared by its probe and re-apply steps
+struct WriteProgress {
+    writes: Vec<PendingVerification>,
+    results: Vec<Option<VerificationResult>>,
+    /// Writes not read back yet, by index
+    pending: Vec<usize>,
+    reads: u32,
+}
+
+impl WriteProgress {
+    fn pending_ids(&self) -> Vec<TrackId<'static>> {
+        self.pending.iter().map(|&i| self.writes[i].track_id.clone()).collect()
+    }
+    
+    /// Count a read and return the tracks it should check, dropping writes that were overtaken
+    fn start_read<A: SpotifyApi>(&mut self, manager: &SpotifyManager<A>) -> Result<Vec<TrackId<'static>>> {
+        self.reads += 1;
+        if self.writes.first().is_some_and(|pending| !Arc::ptr_eq(&manager.liked_cache, &pending.liked_cache)) {
+            info!("👤 Profile switched, dropping verification of {} writes", self.writes.len());
+            return Err(Superseded.into());
+        }
+        
+        let writes = &self.writes;
+        self.pending.retain(|&i| {
+            let latest = manager.is_latest_write(&writes[i]);
+            if !latest {
+                info!("⏭️ {} - {} was changed again, dropping verification of the earlier write", writes[i].track_info.name, writes[i].track_info.artist);
+            }
+            latest
+        });
+        Ok(self.pending_ids())
+    }
+    
+    /// Settle the writes the read shows, returning whether none are left
+    fn finish_read<A: SpotifyApi>(&mut self, manager: &SpotifyManager<A>, statuses: &[bool], verified_after_ms: u64) -> bool {
+        let attempts = self.reads;
+        let (writes, results) = (&self.writes, &mut self.results);
+        let mut statuses = statuses.iter();
+        self.pending.retain(|&i| {
+            if statuses.next() != Some(&writes[i].liked) {
+                return true;
+            }
+            manager.finish_verified(&writes[i]);
+            results[i] = Some(VerificationResult {
+                success: true,
+                subject: writes[i].track_info.clone(),
+                verified_after_ms,
+                attempts,
+            });
+            false
+        });
+        self.pending.is_empty()
+    }
 }
 
-pub struct SpotifyManager {
-    client: AuthCodeSpotify,
-    verification_delay_ms: u64,
-    max_verification_attempts: u32,
+pub struct SpotifyManager<A: SpotifyApi = AuthClient> {
+    client: A,
+    token_lifecycle: TokenLifecycle,
+    rate_limiter: Arc<RateLimiter>,
+    liked_cache: Arc<LikedCache>,
+    /// Local Liked Songs mirror of this profile, `None` if the database couldn't be opened
+    library: Option<Arc<Library>>,
+    /// Likes and unlikes that failed while Spotify was unreachable
+    action_queue: Arc<ActionQueue>,
+    /// Last track `get_current_track` saw and when, what an offline like or unlike applies to
+    last_track: Option<(TrackInfo, Instant)>,
+    /// Priority of the calls currently being made, see `poll_current_track`
+    priority: Priority,
+    /// Scopes the user was already asked for this session without granting them
+    declined_scopes: HashSet<&'static str>,
+    verification: VerificationConfig,
+    /// API latencies and verification outcomes, for the Stats tray item
+    stats: Arc<Stats>,
+    /// Volume before `toggle_mute` muted, restored by the next toggle
+    muted_volume: Option<u8>,
+    /// Generation of the latest like or unlike of each track, see `note_write`
+    latest_writes: HashMap<String, u64>,
+    write_generation: u64,
 }
 
-impl SpotifyManager {
-    /// Create a new Spotify manager with verification
+impl SpotifyManager<AuthClient> {
+    /// Create a new Spotify manager with the configured verification
     pub async fn new(config: &AppConfig) -> Result<Self> {
-        Self::with_config(config, 1000, 8).await  // Increased delay and attempts
+        Self::with_config(config, config.verification.clone()).await
     }
     
     /// Create a new Spotify manager with forced re-authentication
     pub async fn new_with_fresh_auth(config: &AppConfig) -> Result<Self> {
         // Clear any existing cache first
-        if let Ok(cache_path) = Self::get_token_cache_path() {
-            let _ = std::fs::remove_file(cache_path);
+        if token_store::open(&config.token_storage, config.active_profile_name())?.clear().is_ok() {
             info!("🗑️ Cleared existing token cache to force fresh authentication");
         }
-        Self::with_config(config, 750, 3).await
+        let verification = VerificationConfig {
+            base_delay_ms: 750,
+            max_attempts: 3,
+            ..config.verification.clone()
+        };
+        Self::with_config(config, verification).await
     }
     
     /// Create with custom verification settings
-    pub async fn with_config(
-        config: &AppConfig,
-        verification_delay_ms: u64,
-        max_verification_attempts: u32
-    ) -> Result<Self> {
-        let creds = Credentials::new(&config.spotify.client_id, &config.spotify.client_secret);
+    pub async fn with_config(config: &AppConfig, verification: VerificationConfig) -> Result<Self> {
+        let profile = config.active_profile_name();
+        let spotify = config.active_spotify();
         
         let oauth = OAuth {
-            redirect_uri: config.spotify.redirect_uri.clone(),
-            scopes: scopes!(
-                "user-read-currently-playing",
-                "user-read-playback-state",
-                "user-library-modify",
-                "user-library-read",
-                "user-read-private"
-            ),
+            redirect_uri: spotify.redirect_uri.clone(),
+            scopes: permissions::all_scopes(),
             ..Default::default()
         };
         
-        let cache_path = Self::get_token_cache_path()?;
+        let token_store = token_store::open(&config.token_storage, profile)?;
         
-        let config = Config {
-            token_cached: true,           // Enable persistent token caching
-            token_refreshing: true,       // Enable automatic token refresh
-            cache_path,
-            ..Default::default()
+        let defaults = Config::default();
+        let rspotify_config = Config {
+            api_base_url: spotify.api_base_url.clone().unwrap_or(defaults.api_base_url),
+            auth_base_url: spotify.accounts_base_url.clone().unwrap_or(defaults.auth_base_url),
+            token_cached: false,          // Persistence goes through our TokenStore instead
+            token_refreshing: false,      // TokenLifecycle refreshes ahead of expiry instead
+            token_callback_fn: Arc::new(Some(token_store::persist_callback(Arc::clone(&token_store)))),
+            ..defaults
         };
         
-        let mut client = AuthCodeSpotify::with_config(creds, oauth, config);
+        info!("🔑 Using profile \"{}\" with {} authorization flow", profile, spotify.auth_flow);
+        info!("🌐 Spotify endpoints - API: {}, accounts: {}", rspotify_config.api_base_url, rspotify_config.auth_base_url);
+        let mut client = AuthClient::new(
+            spotify.auth_flow,
+            &spotify.client_id,
+            &spotify.client_secret,
+            oauth,
+            rspotify_config,
+            token_store,
+        );
         
         // Handle authentication with persistent tokens
-        Self::ensure_authenticated(&mut client).await?;
+        client.ensure_authenticated().await?;
         
-        Ok(Self {
-            client,
-            verification_delay_ms,
-            max_verification_attempts,
-        })
+        let mut manager = Self::with_api(client, verification);
+        match Library::open(profile) {
+            Ok(library) => manager.attach_library(Arc::new(library)),
+            Err(e) => warn!("⚠️ Liked Songs library unavailable, continuing without it: {:#}", e),
+        }
+        match ActionQueue::open(profile) {
+            Ok(queue) => manager.action_queue = Arc::new(queue),
+            Err(e) => warn!("⚠️ Offline action queue unavailable, queued actions won't survive a restart: {:#}", e),
+        }
+        match Stats::open() {
+            Ok(stats) => manager.stats = Arc::new(stats),
+            Err(e) => warn!("⚠️ Stats file unavailable, this session's stats won't be kept: {:#}", e),
+        }
+        
+        Ok(manager)
+    }
+    
+    /// Clear the token cache and force re-authentication on next use
+    pub fn clear_token_cache(config: &AppConfig) -> Result<()> {
+        let store = token_store::open(&config.token_storage, config.active_profile_name())?;
+        store.clear()?;
+        info!("🗑️ Token cache cleared at: {}", store.location());
+        Ok(())
     }
     
-    /// Ensure client is authenticated, handling token refresh automatically
-    async fn ensure_authenticated(client: &mut AuthCodeSpotify) -> Result<()> {
-        // Try to load cached token first
-        match client.read_token_cache(false).await {
+    /// Check the current token cache status
+    pub async fn check_token_cache_status(config: &AppConfig) -> Result<()> {
+        let store = token_store::open(&config.token_storage, config.active_profile_name())?;
+        
+        match store.load() {
             Ok(Some(token)) => {
-                info!("📁 Loaded cached token");
-                
-                // Debug: Log token details (without exposing sensitive data)
-                info!("🔍 Token debug - access_token length: {}, refresh_token present: {}, expires_at: {:?}", 
-                    token.access_token.len(),
-                    token.refresh_token.is_some(),
-                    token.expires_at);
-                
-                if let Some(ref refresh_token) = token.refresh_token {
-                    info!("🔍 Refresh token length: {}", refresh_token.len());
-                } else {
-                    warn!("🔍 Refresh token is None");
-                }
-                
-                // Check if we have both access and refresh tokens
-                if token.access_token.is_empty() {
-                    warn!("❌ Cached token is missing access token, re-authenticating...");
-                    Self::authenticate_first_time(client).await?;
-                    return Ok(());
-                }
-                
-                if token.refresh_token.is_none() || token.refresh_token.as_ref().unwrap().is_empty() {
-                    warn!("❌ Cached token is missing refresh token, re-authenticating...");
-                    Self::authenticate_first_time(client).await?;
-                    return Ok(());
-                }
-                
-                // CRITICAL FIX: Set the token in the client's internal state
-                // The read_token_cache only reads from file but doesn't set it in the client
-                *client.get_token().lock().await.unwrap() = Some(token.clone());
-                info!("🔧 Token set in client internal state");
-                
-                // Test the token by making a simple API call
-                match client.current_user().await {
-                    Ok(user) => {
-                        info!("✅ Token is valid for user: {}", 
-                            user.display_name.unwrap_or_else(|| "Unknown".to_string()));
-                    }
-                    Err(_) => {
-                        warn!("🔄 Token expired, attempting refresh...");
-                        match client.refresh_token().await {
-                            Ok(_) => {
-                                info!("✅ Token refreshed successfully");
-                                client.write_token_cache().await
-                                    .context("Failed to save refreshed token")?;
-                            }
-                            Err(e) => {
-                                warn!("❌ Token refresh failed: {}, need to re-authenticate", e);
-                                Self::authenticate_first_time(client).await?;
-                            }
-                        }
-                    }
-                }
+                let presence = |value: &str| if value.is_empty() { "empty" } else { "present" };
+                println!("✅ Token cache found at: {}", store.location());
+                println!("📊 Cache contents:");
+                println!("  - access_token: {}", presence(&token.access_token));
+                println!("  - refresh_token: {}", 
+                    token.refresh_token.as_deref().map(presence).unwrap_or("missing"));
+                println!("  - expires_at: {}", 
+                    token.expires_at.map(|t| t.to_rfc3339()).unwrap_or_else(|| "missing".to_string()));
             }
             Ok(None) => {
-                info!("🔐 No cached token found, starting initial authentication...");
-                Self::authenticate_first_time(client).await?;
+                println!("❌ No token cache found at: {}", store.location());
             }
             Err(e) => {
-                warn!("❌ Failed to read token cache: {}, starting initial authentication...", e);
-                Self::authenticate_first_time(client).await?;
+                println!("❌ Failed to read token cache at {}: {}", store.location(), e);
             }
         }
         
         Ok(())
     }
+}
+
+/// Lets `verify_writes` run on a shared manager as well as a borrowed one
+impl<A: SpotifyApi> AsMut<Self> for SpotifyManager<A> {
+    fn as_mut(&mut self) -> &mut Self {
+        self
+    }
+}
+
+impl<A: SpotifyApi> SpotifyManager<A> {
+    /// Wrap an already authenticated API client
+    pub fn with_api(client: A, verification: VerificationConfig) -> Self {
+        Self {
+            client,
+            token_lifecycle: TokenLifecycle::default(),
+            rate_limiter: Arc::new(RateLimiter::default()),
+            liked_cache: Arc::new(LikedCache::default()),
+            library: None,
+            action_queue: Arc::new(ActionQueue::default()),
+            last_track: None,
+            priority: Priority::User,
+            declined_scopes: HashSet::new(),
+            verification,
+            stats: Arc::new(Stats::default()),
+            muted_volume: None,
+            latest_writes: HashMap::new(),
+            write_generation: 0,
+        }
+    }
     
-    /// Handle first-time authentication (only runs once)
-    async fn authenticate_first_time(client: &mut AuthCodeSpotify) -> Result<()> {
-        // Clear any existing invalid cache by removing cached file
-        if let Ok(cache_path) = Self::get_token_cache_path() {
-            let _ = std::fs::remove_file(cache_path);
-        }
-        
-        let url = client.get_authorize_url(true)?;  // Use state parameter for security
-        
-        println!("\n🔐 Spotify Authentication Required (One-time setup)");
-        println!("1. Your browser will open to Spotify's login page");
-        println!("2. Log in and authorize the application");
-        println!("3. You'll be redirected to a page that won't load - that's normal!");
-        println!("4. Copy the ENTIRE URL from your browser's address bar");
-        println!("5. Paste it here when prompted\n");
-        
-        // Open browser automatically
-        if let Err(e) = webbrowser::open(&url) {
-            warn!("Failed to open browser automatically: {}", e);
-            println!("Please manually open this URL: {}", url);
-        }
-        
-        // Get redirect URL from user
-        println!("Paste the redirect URL here:");
-        let mut input = String::new();
-        std::io::stdin().read_line(&mut input)?;
-        let redirect_url = input.trim();
-        
-        // Parse authorization code from the URL
-        let parsed_url = Url::parse(redirect_url)
-            .context("Invalid URL. Please make sure you copied the complete URL from your browser.")?;
-        
-        let code = parsed_url
-            .query_pairs()
-            .find(|(key, _)| key == "code")
-            .map(|(_, value)| value.into_owned())
-            .ok_or_else(|| anyhow!("No authorization code found in URL. Please make sure you copied the complete redirect URL."))?;
-        
-        // Exchange authorization code for tokens
-        client.request_token(&code).await
-            .context("Failed to exchange authorization code for tokens")?;
-        
-        // Immediately check if we got a refresh token
-        match client.get_token().lock().await.unwrap().as_ref() {
-            Some(token) => {
-                info!("🔍 Token obtained - access_token length: {}, refresh_token present: {}", 
-                    token.access_token.len(),
-                    token.refresh_token.is_some());
-                
-                if token.refresh_token.is_none() {
-                    return Err(anyhow!("Authorization completed but no refresh token was provided by Spotify. This may be due to an app configuration issue."));
-                }
-                
-                if let Some(ref refresh_token) = token.refresh_token {
-                    if refresh_token.is_empty() {
-                        return Err(anyhow!("Authorization completed but refresh token is empty."));
-                    }
-                    info!("✅ Valid refresh token obtained (length: {})", refresh_token.len());
-                }
-            }
-            None => {
-                return Err(anyhow!("Authorization completed but no token was set in client"));
-            }
+    /// The wrapped API client, for tests to inspect their fake
+    #[cfg(test)]
+    pub fn api(&self) -> &A {
+        &self.client
+    }
+    
+    /// Make sure the token carries the scopes `action` needs
+    ///
+    /// User actions fail with `ConsentNeeded` so `with_consent` can ask for them,
+    /// background calls fail with `MissingPermission` instead of asking.
+    async fn require_scopes(&mut self, action: Action) -> Result<()> {
+        let granted = match self.client.token().await {
+            Some(token) => token.scopes,
+            None => return Ok(()),
+        };
+        
+        // Older caches may not record scopes, let the API decide then
+        if granted.is_empty() {
+            return Ok(());
         }
         
-        // Save tokens to cache for future use
-        client.write_token_cache().await
-            .context("Failed to save tokens to cache")?;
+        let missing = action.missing_scopes(&granted);
+        if missing.is_empty() {
+            return Ok(());
+        }
         
-        // Verify the token was saved correctly by reading it back
-        match client.read_token_cache(false).await {
-            Ok(Some(token)) => {
-                let has_access = !token.access_token.is_empty();
-                let has_refresh = token.refresh_token.is_some() && !token.refresh_token.as_ref().unwrap().is_empty();
-                info!("Token verification: access_token={}, refresh_token={}", has_access, has_refresh);
-                
-                if !has_access || !has_refresh {
-                    warn!("⚠️ Saved token is incomplete - this may cause re-authentication on next run");
-                }
-            }
-            Ok(None) => {
-                warn!("⚠️ No token found after saving - this may cause re-authentication on next run");
-            }
-            Err(e) => {
-                warn!("⚠️ Failed to verify saved token: {} - this may cause re-authentication on next run", e);
-            }
+        // Don't reopen the browser on every poll once the user said no
+        if missing.iter().any(|scope| self.declined_scopes.contains(scope)) {
+            return Err(MissingPermission { action, missing }.into());
         }
         
-        println!("✅ Authentication successful! Token cached for future use.");
-        println!("🎉 You'll never need to authenticate again (unless you revoke access)!\n");
+        // Only a user action may open the browser, never the poller or a verification behind the lock
+        if self.priority == Priority::Background {
+            return Err(MissingPermission { action, missing }.into());
+        }
         
-        Ok(())
+        // The browser consent runs in `with_consent`, which doesn't hold the manager for it
+        warn!("🔒 Token lacks {:?} needed to {}", missing, action);
+        Err(ConsentNeeded { action, missing }.into())
+    }
+    
+    /// Run a user action, asking for permissions it lacks in between
+    ///
+    /// The browser consent can take minutes, so the manager is unlocked while it
+    /// runs and the action starts over once the new token is installed.
+    pub async fn with_consent<T, F>(manager: &Mutex<Self>, mut run: F) -> Result<T>
+    where
+        F: for<'m> FnMut(&'m mut Self) -> Step<'m, T>,
+    {
+        let result = run(&mut *manager.lock().await).await;
+        let needed = match &result {
+            Err(e) => match e.downcast_ref::<ConsentNeeded>() {
+                Some(needed) => needed.clone(),
+                None => return result,
+            },
+            Ok(_) => return result,
+        };
+        
+        Self::request_consent(manager, needed).await?;
+        run(&mut *manager.lock().await).await
     }
     
-    /// Get the path for token cache
-    fn get_token_cache_path() -> Result<PathBuf> {
-        let cache_dir = dirs::cache_dir()
-            .context("Failed to get system cache directory")?;
-        
-        let app_cache_dir = cache_dir.join("spotify-quick-actions");
-        fs::create_dir_all(&app_cache_dir)
-            .context("Failed to create application cache directory")?;
-        
-        Ok(app_cache_dir.join("spotify_token.json"))
-    }
-    
-    /// Ensure token is valid before making API calls
-    async fn ensure_token_valid(&mut self) -> Result<()> {
-        // The rspotify library with token_refreshing: true should handle this automatically,
-        // but we can add an extra check if needed
-        match self.client.current_user().await {
-            Ok(_) => Ok(()),
-            Err(_) => {
-                warn!("🔄 Token validation failed, attempting refresh...");
-                self.client.refresh_token().await
-                    .context("Failed to refresh token")?;
-                self.client.write_token_cache().await
-                    .context("Failed to save refreshed token")?;
-                info!("✅ Token refreshed successfully");
-                Ok(())
+    /// Ask for the missing scopes in the browser with the manager unlocked, then install the new token
+    async fn request_consent(manager: &Mutex<Self>, needed: ConsentNeeded) -> Result<()> {
+        let ConsentNeeded { action, missing } = needed;
+        let login = {
+            let manager = manager.lock().await;
+            let mut scopes = manager.client.token().await
+                .map(|token| token.scopes)
+                .unwrap_or_default();
+            scopes.extend(missing.iter().map(|scope| scope.to_string()));
+            manager.client.reauthorize(scopes)
+        };
+        
+        info!("🔒 Requesting re-authorization to {}", action);
+        let _ = Notification::new()
+            .summary("🔒 Spotify permission needed")
+            .body(&format!("To {} the app needs additional permissions. Please approve them in your browser.", action))
+            .timeout(5000)
+            .show();
+        
+        let result = login.await;
+        let mut manager = manager.lock().await;
+        let token = match result {
+            Ok(token) => token,
+            Err(e) => {
+                warn!("❌ Re-authorization for additional scopes failed: {}", e);
+                manager.declined_scopes.extend(missing.iter().copied());
+                return Err(MissingPermission { action, missing }.into());
             }
+        };
+        
+        let still_missing = action.missing_scopes(&token.scopes);
+        manager.client.set_token(token).await;
+        if !still_missing.is_empty() {
+            manager.declined_scopes.extend(still_missing.iter().copied());
+            return Err(MissingPermission { action, missing: still_missing }.into());
         }
+        
+        info!("✅ Additional permissions granted for: {}", action);
+        Ok(())
     }
     
     /// Get current playing track
     pub async fn get_current_track(&mut self) -> Result<TrackInfo> {
-        self.ensure_token_valid().await?;
+        self.require_scopes(Action::ReadCurrentTrack).await?;
         
-        let currently_playing = self.client
-            .current_playing(None, None::<Vec<_>>)
-            .await
+        let currently_playing = authorized!(self, client => client.current_playing().await)
             .context("Failed to get currently playing track")?;
         
-        match currently_playing {
-            Some(CurrentlyPlayingContext {
-                item: Some(PlayableItem::Track(track)),
-                ..
-            }) => {
-                let track_info = TrackInfo {
-                    id: track.id.as_ref().map(|id| id.to_string()),
-                    name: track.name.clone(),
-                    artist: track.artists.first()
-                        .map(|a| a.name.clone())
-                        .unwrap_or_else(|| "Unknown Artist".to_string()),
-                    uri: track.id.as_ref().map(|id| format!("spotify:track:{}", id.id())),
-                };
-                
+        match currently_playing.and_then(|playing| TrackInfo::from_item(playing.item.as_ref())) {
+            Some(track_info) => {
                 info!("Current track: {} - {}", track_info.name, track_info.artist);
+                self.last_track = Some((track_info.clone(), Instant::now()));
                 Ok(track_info)
             }
-            _ => Err(anyhow!("No track currently playing"))
+            None => Err(anyhow!("No track currently playing"))
         }
     }
     
-    /// Like current track with verification
-    pub async fn like_current_track(&mut self) -> Result<TrackInfo> {
-        self.ensure_token_valid().await?;
+    /// Pause if something is playing, resume otherwise; returns whether playback is running now
+    pub async fn toggle_playback(&mut self) -> Result<bool> {
+        self.require_scopes(Action::ControlPlayback).await?;
         
-        let track_info = self.get_current_track().await?;
+        let is_playing = authorized!(self, client => client.current_playing().await)
+            .context("Failed to get current playback")?
+            .is_some_and(|context| context.is_playing);
         
-        if let Some(track_id_str) = &track_info.id {
-            let track_id = self.parse_track_id(track_id_str)?;
-            
-            info!("🎯 Attempting to LIKE track: {} - {} (ID: {})", track_info.name, track_info.artist, track_id.id());
-            
-            // Attempt to like the track
-            self.client
-                .current_user_saved_tracks_add([track_id.clone()])
-                .await
-                .context("Failed to add track to saved tracks")?;
-            
-            info!("📡 LIKE API call completed, starting verification...");
-            
-            // Verify the operation with retries
-            let verification_result = self.verify_track_liked(&track_id, &track_info).await?;
-            
-            if verification_result.success {
-                info!("✅ Successfully liked and verified: {} - {} (verified in {}ms after {} attempts)", 
-                    track_info.name, track_info.artist, 
-                    verification_result.verified_after_ms,
-                    verification_result.attempts);
-                Ok(track_info)
-            } else {
-                error!("❌ Failed to verify track was liked: {} - {}", track_info.name, track_info.artist);
-                Err(anyhow!("Track like operation failed verification - the track may not have been saved to your library"))
-            }
+        let started = std::time::Instant::now();
+        let result = if is_playing {
+            authorized!(self, client => client.pause_playback().await)
         } else {
-            Err(anyhow!("Current track has no ID"))
-        }
+            authorized!(self, client => client.resume_playback().await)
+        };
+        self.finish_player_command(if is_playing { "pause" } else { "play" }, started, result)?;
+        
+        info!("{} Playback {}", if is_playing { "⏸️" } else { "▶️" }, if is_playing { "paused" } else { "resumed" });
+        Ok(!is_playing)
     }
     
-    /// Save current track (alias for like_current_track for compatibility)
-    pub async fn save_current_track(&mut self) -> Result<TrackInfo> {
-        self.like_current_track().await
+    /// Skip to the next track
+    pub async fn next_track(&mut self) -> Result<()> {
+        self.require_scopes(Action::ControlPlayback).await?;
+        
+        let started = std::time::Instant::now();
+        let result = authorized!(self, client => client.next_track().await);
+        self.finish_player_command("next", started, result)
     }
     
-    /// Unlike current track with verification
-    pub async fn unlike_current_track(&mut self) -> Result<TrackInfo> {
-        self.ensure_token_valid().await?;
+    /// Skip to the previous track
+    pub async fn previous_track(&mut self) -> Result<()> {
+        self.require_scopes(Action::ControlPlayback).await?;
         
-        let track_info = self.get_current_track().await?;
+        let started = std::time::Instant::now();
+        let result = authorized!(self, client => client.previous_track().await);
+        self.finish_player_command("previous", started, result)
+    }
+    
+    /// Seek `offset_ms` forward (or back, if negative) in the playing item, returns where it landed
+    pub async fn seek_by(&mut self, offset_ms: i64) -> Result<SeekPosition> {
+        let position = self.playing_position().await?.offset_by(offset_ms);
+        self.seek_to(position).await
+    }
+    
+    /// Start the playing item over
+    pub async fn restart_track(&mut self) -> Result<SeekPosition> {
+        let position = SeekPosition { position_ms: 0, ..self.playing_position().await? };
+        self.seek_to(position).await
+    }
+    
+    /// Progress and length of the playing track or episode
+    async fn playing_position(&mut self) -> Result<SeekPosition> {
+        self.require_scopes(Action::ControlPlayback).await?;
         
-        if let Some(track_id_str) = &track_info.id {
-            let track_id = self.parse_track_id(track_id_str)?;
-            
-            info!("🎯 Attempting to UNLIKE track: {} - {} (ID: {})", track_info.name, track_info.artist, track_id.id());
-            
-            // Attempt to unlike the track
-            self.client
-                .current_user_saved_tracks_delete([track_id.clone()])
-                .await
-                .context("Failed to remove track from saved tracks")?;
-            
-            info!("📡 UNLIKE API call completed, starting verification...");
-            
-            // Verify the operation with retries
-            let verification_result = self.verify_track_unliked(&track_id, &track_info).await?;
-            
-            if verification_result.success {
-                info!("✅ Successfully unliked and verified: {} - {} (verified in {}ms after {} attempts)", 
-                    track_info.name, track_info.artist,
-                    verification_result.verified_after_ms,
-                    verification_result.attempts);
-                Ok(track_info)
-            } else {
-                error!("❌ Failed to verify track was unliked: {} - {}", track_info.name, track_info.artist);
-                Err(anyhow!("Track unlike operation failed verification - the track may still be in your library"))
-            }
+        let currently_playing = authorized!(self, client => client.current_playing().await)
+            .context("Failed to get currently playing track")?
+            .ok_or(NoActiveDevice)?;
+        let duration = match &currently_playing.item {
+            Some(PlayableItem::Track(track)) => track.duration,
+            Some(PlayableItem::Episode(episode)) => episode.duration,
+            None => return Err(anyhow!("Nothing to seek in is playing")),
+        };
+        
+        let millis = |duration: chrono::Duration| duration.num_milliseconds().clamp(0, i64::from(u32::MAX)) as u32;
+        Ok(SeekPosition {
+            position_ms: currently_playing.progress.map(millis).unwrap_or(0),
+            duration_ms: millis(duration),
+        })
+    }
+    
+    async fn seek_to(&mut self, position: SeekPosition) -> Result<SeekPosition> {
+        let started = std::time::Instant::now();
+        let result = authorized!(self, client => client.seek_track(position.position_ms).await);
+        self.finish_player_command("seek", started, result)?;
+        
+        info!("⏩ Seeked to {}", position);
+        Ok(position)
+    }
+    
+    /// Change the active device's volume by `delta` percentage points, returns the new volume
+    pub async fn change_volume(&mut self, delta: i32) -> Result<u8> {
+        let volume = playback::step_volume(self.current_volume().await?, delta);
+        self.set_volume(volume).await?;
+        
+        info!("🔊 Volume set to {}%", volume);
+        Ok(volume)
+    }
+    
+    /// Mute, or restore the volume from before muting; returns the new volume
+    pub async fn toggle_mute(&mut self) -> Result<u8> {
+        let current = self.current_volume().await?;
+        
+        // Whatever set the volume to zero counts as muted, it may have been another device
+        let volume = if current == 0 {
+            self.muted_volume.take().unwrap_or(playback::UNMUTE_FALLBACK_VOLUME)
         } else {
-            Err(anyhow!("Current track has no ID"))
-        }
+            self.muted_volume = Some(current);
+            0
+        };
+        self.set_volume(volume).await?;
+        
+        info!("{} Volume set to {}%", if volume == 0 { "🔇" } else { "🔊" }, volume);
+        Ok(volume)
     }
     
-    /// Check if a track is currently liked
-    pub async fn is_track_liked(&mut self, track_id: &TrackId<'_>) -> Result<bool> {
-        self.ensure_token_valid().await?;
+    /// Volume of the active device, read from the playback state
+    async fn current_volume(&mut self) -> Result<u8> {
+        self.require_scopes(Action::ControlPlayback).await?;
         
-        info!("🔍 Checking if track is liked: {}", track_id.id());
+        let playback = self.active_playback().await?;
+        let volume = playback.device.volume_percent
+            .ok_or_else(|| anyhow!("{} doesn't support changing the volume", playback.device.name))?;
+        Ok(volume.min(100) as u8)
+    }
+    
+    /// Shuffle and repeat mode of the active device, `None` if there is none
+    pub async fn player_modes(&mut self) -> Result<Option<PlayerModes>> {
+        self.require_scopes(Action::ReadCurrentTrack).await?;
         
-        let is_saved = self.client
-            .current_user_saved_tracks_contains([track_id.clone()])
-            .await
-            .context("Failed to check if track is saved")?;
+        let playback = authorized!(self, client => client.current_playback().await)
+            .context("Failed to get playback state")?;
+        Ok(playback.as_ref().map(PlayerModes::from))
+    }
+    
+    /// Turn shuffle on if it's off and off if it's on, returns the modes now in effect
+    pub async fn toggle_shuffle(&mut self) -> Result<PlayerModes> {
+        self.require_scopes(Action::ControlPlayback).await?;
         
-        let result = is_saved.first() == Some(&true);
-        info!("🔍 Track liked status: {} = {}", track_id.id(), result);
+        let current = PlayerModes::from(&self.active_playback().await?);
+        let modes = PlayerModes { shuffle: !current.shuffle, ..current };
         
-        Ok(result)
+        let started = std::time::Instant::now();
+        let result = authorized!(self, client => client.set_shuffle(modes.shuffle).await);
+        self.finish_player_command("shuffle", started, result)?;
+        
+        info!("🔀 Shuffle {}", if modes.shuffle { "on" } else { "off" });
+        Ok(modes)
     }
     
-    /// Verify that a like operation succeeded with enhanced retry logic
-    async fn verify_track_liked(&mut self, track_id: &TrackId<'_>, track_info: &TrackInfo) -> Result<VerificationResult> {
-        let start_time = std::time::Instant::now();
-        info!("🔍 Starting verification for LIKE operation: {} - {}", track_info.name, track_info.artist);
-        
-        // Check current state before starting verification
-        match self.is_track_liked(track_id).await {
-            Ok(true) => {
-                info!("✅ Track was already liked before verification attempts");
-                return Ok(VerificationResult {
-                    success: true,
-                    track_info: track_info.clone(),
-                    verified_after_ms: start_time.elapsed().as_millis() as u64,
-                    attempts: 0,
-                });
-            }
-            Ok(false) => {
-                info!("⏳ Track not yet liked, starting verification attempts...");
-            }
-            Err(e) => {
-                warn!("⚠️ Initial verification check failed: {}", e);
-            }
-        }
+    /// Move repeat on to the next mode (off → context → track), returns the modes now in effect
+    pub async fn cycle_repeat(&mut self) -> Result<PlayerModes> {
+        self.require_scopes(Action::ControlPlayback).await?;
         
-        for attempt in 1..=self.max_verification_attempts {
-            // Progressive delay: start with base delay, increase each attempt
-            let delay = self.verification_delay_ms + (attempt - 1) as u64 * 500;
-            info!("⏳ Verification attempt {}/{} - waiting {}ms...", attempt, self.max_verification_attempts, delay);
-            sleep(Duration::from_millis(delay)).await;
-            
-            match self.is_track_liked(track_id).await {
-                Ok(true) => {
-                    let elapsed_ms = start_time.elapsed().as_millis() as u64;
-                    info!("✅ LIKE verified successfully after {}ms and {} attempts", elapsed_ms, attempt);
-                    return Ok(VerificationResult {
-                        success: true,
-                        track_info: track_info.clone(),
-                        verified_after_ms: elapsed_ms,
-                        attempts: attempt,
-                    });
-                }
-                Ok(false) => {
-                    warn!("❌ Attempt {}/{}: Track still not liked, retrying...", attempt, self.max_verification_attempts);
-                    
-                    // If we're on the last few attempts, try re-liking the track
-                    if attempt >= self.max_verification_attempts - 2 {
-                        warn!("🔄 Re-attempting like operation on attempt {}", attempt);
-                        if let Err(e) = self.client.current_user_saved_tracks_add([track_id.clone()]).await {
-                            warn!("⚠️ Re-like attempt failed: {}", e);
-                        } else {
-                            info!("🔄 Re-like operation completed");
-                        }
-                    }
-                }
-                Err(e) => {
-                    warn!("⚠️ Attempt {}/{}: Verification API call failed: {}", attempt, self.max_verification_attempts, e);
-                }
-            }
-        }
+        let current = PlayerModes::from(&self.active_playback().await?);
+        let modes = PlayerModes { repeat: playback::next_repeat(current.repeat), ..current };
         
-        let elapsed_ms = start_time.elapsed().as_millis() as u64;
-        error!("❌ LIKE verification failed after {} attempts and {}ms", self.max_verification_attempts, elapsed_ms);
-        Ok(VerificationResult {
-            success: false,
-            track_info: track_info.clone(),
-            verified_after_ms: elapsed_ms,
-            attempts: self.max_verification_attempts,
-        })
+        let started = std::time::Instant::now();
+        let result = authorized!(self, client => client.set_repeat(modes.repeat).await);
+        self.finish_player_command("repeat", started, result)?;
+        
+        info!("{}", playback::repeat_label(modes.repeat));
+        Ok(modes)
     }
     
-    /// Verify that an unlike operation succeeded with enhanced retry logic
-    async fn verify_track_unliked(&mut self, track_id: &TrackId<'_>, track_info: &TrackInfo) -> Result<VerificationResult> {
-        let start_time = std::time::Instant::now();
-        info!("🔍 Starting verification for UNLIKE operation: {} - {}", track_info.name, track_info.artist);
-        
-        // Check current state before starting verification
-        match self.is_track_liked(track_id).await {
-            Ok(false) => {
-                info!("✅ Track was already unliked before verification attempts");
-                return Ok(VerificationResult {
-                    success: true,
-                    track_info: track_info.clone(),
-                    verified_after_ms: start_time.elapsed().as_millis() as u64,
-                    attempts: 0,
-                });
-            }
-            Ok(true) => {
-                info!("⏳ Track still liked, starting verification attempts...");
-            }
-            Err(e) => {
-                warn!("⚠️ Initial verification check failed: {}", e);
-            }
+    /// Spotify Connect devices available to the user
+    pub async fn devices(&mut self) -> Result<Vec<Device>> {
+        self.require_scopes(Action::ListDevices).await?;
+        
+        authorized!(self, client => client.devices().await)
+            .context("Failed to get available devices")
+    }
+    
+    /// Background variant of `devices` for the tray refresh: never waits on a rate limit
+    pub async fn poll_devices(&mut self) -> Result<Vec<Device>> {
+        self.priority = Priority::Background;
+        let result = self.devices().await;
+        self.priority = Priority::User;
+        result
+    }
+    
+    /// Move playback to the device with `device_id`
+    pub async fn transfer_playback(&mut self, device_id: &str) -> Result<()> {
+        self.require_scopes(Action::ControlPlayback).await?;
+        
+        let started = std::time::Instant::now();
+        let result = authorized!(self, client => client.transfer_playback(device_id).await);
+        self.finish_player_command("transfer", started, result)?;
+        
+        info!("🔈 Playback transferred to {}", device_id);
+        Ok(())
+    }
+    
+    /// Move playback to `preferred` (a device name or ID) unless something is playing already
+    ///
+    /// Returns the device playback moved to, `None` if nothing had to move.
+    pub async fn transfer_to_preferred_device(&mut self, preferred: &str) -> Result<Option<Device>> {
+        self.require_scopes(Action::ControlPlayback).await?;
+        
+        let playback = authorized!(self, client => client.current_playback().await)
+            .context("Failed to get playback state")?;
+        if playback.is_some_and(|playback| playback.is_playing) {
+            info!("▶️ Already playing, staying on the current device");
+            return Ok(None);
         }
         
-        for attempt in 1..=self.max_verification_attempts {
-            // Progressive delay: start with base delay, increase each attempt
-            let delay = self.verification_delay_ms + (attempt - 1) as u64 * 500;
-            info!("⏳ Verification attempt {}/{} - waiting {}ms...", attempt, self.max_verification_attempts, delay);
-            sleep(Duration::from_millis(delay)).await;
-            
-            match self.is_track_liked(track_id).await {
-                Ok(false) => {
-                    let elapsed_ms = start_time.elapsed().as_millis() as u64;
-                    info!("✅ UNLIKE verified successfully after {}ms and {} attempts", elapsed_ms, attempt);
-                    return Ok(VerificationResult {
-                        success: true,
-                        track_info: track_info.clone(),
-                        verified_after_ms: elapsed_ms,
-                        attempts: attempt,
-                    });
-                }
-                Ok(true) => {
-                    warn!("❌ Attempt {}/{}: Track still liked, retrying...", attempt, self.max_verification_attempts);
-                    
-                    // If we're on the last few attempts, try re-unliking the track
-                    if attempt >= self.max_verification_attempts - 2 {
-                        warn!("🔄 Re-attempting unlike operation on attempt {}", attempt);
-                        if let Err(e) = self.client.current_user_saved_tracks_delete([track_id.clone()]).await {
-                            warn!("⚠️ Re-unlike attempt failed: {}", e);
-                        } else {
-                            info!("🔄 Re-unlike operation completed");
-                        }
-                    }
-                }
-                Err(e) => {
-                    warn!("⚠️ Attempt {}/{}: Verification API call failed: {}", attempt, self.max_verification_attempts, e);
-                }
-            }
+        let device = self.devices().await?
+            .into_iter()
+            .find(|device| device.id.as_deref() == Some(preferred) || device.name.eq_ignore_ascii_case(preferred))
+            .ok_or_else(|| anyhow!("Preferred device \"{}\" isn't available", preferred))?;
+        if device.is_active {
+            return Ok(None);
         }
+        let Some(device_id) = device.id.clone() else {
+            return Err(anyhow!("{} can't be controlled through the Web API", device.name));
+        };
         
-        let elapsed_ms = start_time.elapsed().as_millis() as u64;
-        error!("❌ UNLIKE verification failed after {} attempts and {}ms", self.max_verification_attempts, elapsed_ms);
-        Ok(VerificationResult {
-            success: false,
-            track_info: track_info.clone(),
-            verified_after_ms: elapsed_ms,
-            attempts: self.max_verification_attempts,
-        })
+        self.transfer_playback(&device_id).await?;
+        Ok(Some(device))
     }
     
-    /// Parse various track ID formats
-    fn parse_track_id<'a>(&self, track_id_str: &'a str) -> Result<TrackId<'a>> {
-        // Handle different track ID formats
-        if track_id_str.starts_with("spotify:track:") {
-            TrackId::from_uri(track_id_str)
-                .context("Failed to parse Spotify track URI")
-        } else if track_id_str.len() == 22 && track_id_str.chars().all(|c| c.is_alphanumeric()) {
-            TrackId::from_id(track_id_str)
-                .context("Failed to parse raw Spotify track ID")
-        } else {
-            // Try to extract ID from URL or other formats
-            let clean_id = track_id_str
-                .split('/')
-                .last()
-                .unwrap_or(track_id_str)
-                .split('?')
-                .next()
-                .unwrap_or(track_id_str);
-            
-            TrackId::from_id(clean_id)
-                .context("Failed to parse track ID from URL format")
-        }
+    /// Playback state of the active device
+    async fn active_playback(&mut self) -> Result<CurrentPlaybackContext> {
+        let playback = authorized!(self, client => client.current_playback().await)
+            .context("Failed to get playback state")?
+            .ok_or(NoActiveDevice)?;
+        Ok(playback)
     }
     
-    /// Get current user info (useful for testing authentication)
-    pub async fn get_current_user(&mut self) -> Result<rspotify::model::PrivateUser> {
-        self.ensure_token_valid().await?;
-        Ok(self.client.current_user().await?)
+    async fn set_volume(&mut self, volume: u8) -> Result<()> {
+        let started = std::time::Instant::now();
+        let result = authorized!(self, client => client.set_volume(volume).await);
+        self.finish_player_command("volume", started, result)
     }
     
-    /// Force a token refresh (useful for testing)
-    pub async fn refresh_token(&mut self) -> Result<()> {
-        self.client.refresh_token().await?;
-        self.client.write_token_cache().await?;
-        info!("✅ Token manually refreshed");
-        Ok(())
+    /// Record a player command's latency, turning Spotify's 404 into `NoActiveDevice`
+    fn finish_player_command(&self, command: &str, started: std::time::Instant, result: Result<()>) -> Result<()> {
+        self.stats.record_api_call(command, started.elapsed().as_millis() as u64, result.is_ok());
+        result
+            .map_err(playback::explain_no_device)
+            .with_context(|| format!("Failed to send \"{}\" to Spotify", command))
     }
     
-    /// Clear the token cache and force re-authentication on next use
-    pub fn clear_token_cache() -> Result<()> {
-        let cache_path = Self::get_token_cache_path()?;
-        if cache_path.exists() {
-            std::fs::remove_file(&cache_path)
-                .context("Failed to remove token cache file")?;
-            info!("🗑️ Token cache cleared at: {}", cache_path.display());
-        } else {
-            info!("ℹ️ No token cache file found to clear");
-        }
-        Ok(())
+    /// Current track and player modes for the poller, from a single playback request that never waits on a rate limit
+    pub async fn poll_current_track(&mut self) -> Result<PolledPlayback> {
+        self.priority = Priority::Background;
+        let result = self.fetch_playback().await;
+        self.priority = Priority::User;
+        result
     }
     
-    /// Check the current token cache status
-    pub async fn check_token_cache_status() -> Result<()> {
-        let cache_path = Self::get_token_cache_path()?;
+    async fn fetch_playback(&mut self) -> Result<PolledPlayback> {
+        self.require_scopes(Action::ReadCurrentTrack).await?;
         
-        if !cache_path.exists() {
-            println!("❌ No token cache file found at: {}", cache_path.display());
-            return Ok(());
+        let playback = authorized!(self, client => client.current_playback().await)
+            .context("Failed to get playback state")?;
+        
+        let track = playback.as_ref().and_then(|playback| TrackInfo::from_item(playback.item.as_ref()));
+        if let Some(track_info) = &track {
+            self.last_track = Some((track_info.clone(), Instant::now()));
         }
+        Ok(PolledPlayback { track, modes: playback.as_ref().map(PlayerModes::from) })
+    }
+    
+    /// Rate limiter shared by everything talking to Spotify through this manager
+    pub fn rate_limiter(&self) -> Arc<RateLimiter> {
+        Arc::clone(&self.rate_limiter)
+    }
+    
+    /// Use an existing rate limiter, e.g. the previous manager's when switching profiles
+    pub fn share_rate_limiter(&mut self, rate_limiter: Arc<RateLimiter>) {
+        self.rate_limiter = rate_limiter;
+    }
+    
+    /// Keep `library` in step with our own likes and use it for liked-status lookups
+    pub fn attach_library(&mut self, library: Arc<Library>) {
+        self.library = Some(library);
+    }
+    
+    /// Liked Songs mirror of this profile, synced by `library::sync`
+    pub fn library(&self) -> Option<Arc<Library>> {
+        self.library.clone()
+    }
+    
+    /// One page of Liked Songs for the library sync, made with background priority
+    pub async fn saved_tracks_page(&mut self, offset: u32, limit: u32) -> Result<Page<SavedTrack>> {
+        self.priority = Priority::Background;
+        let result = self.fetch_saved_tracks_page(offset, limit).await;
+        self.priority = Priority::User;
+        result
+    }
+    
+    async fn fetch_saved_tracks_page(&mut self, offset: u32, limit: u32) -> Result<Page<SavedTrack>> {
+        self.require_scopes(Action::SyncLibrary).await?;
         
-        // Try to read and parse the cache file
-        match std::fs::read_to_string(&cache_path) {
-            Ok(content) => {
-                match serde_json::from_str::<serde_json::Value>(&content) {
-                    Ok(json) => {
-                        println!("✅ Token cache file found at: {}", cache_path.display());
-                        println!("📊 Cache contents:");
-                        println!("  - access_token: {}", 
-                            json.get("access_token").and_then(|v| v.as_str()).map(|s| if s.is_empty() { "empty" } else { "present" }).unwrap_or("missing"));
-                        println!("  - refresh_token: {}", 
-                            json.get("refresh_token").and_then(|v| v.as_str()).map(|s| if s.is_empty() { "empty" } else { "present" }).unwrap_or("missing"));
-                        println!("  - expires_at: {}", 
-                            json.get("expires_at").and_then(|v| v.as_str()).unwrap_or("missing"));
-                    }
-                    Err(e) => {
-                        println!("❌ Token cache file is corrupted: {}", e);
-                    }
-                }
-            }
-            Err(e) => {
-                println!("❌ Failed to read token cache file: {}", e);
-            }
+        authorized!(self, client => client.saved_tracks(limit, offset).await)
+            .context("Failed to fetch saved tracks")
+    }
+    
+    /// API latencies and verification outcomes, shown by the Stats tray item
+    pub fn stats(&self) -> Arc<Stats> {
+        Arc::clone(&self.stats)
+    }
+    
+    /// Offline actions of this profile, replayed by `action_queue::replay`
+    pub fn action_queue(&self) -> Arc<ActionQueue> {
+        Arc::clone(&self.action_queue)
+    }
+    
+    /// Queue `action` for the last known track after it failed because Spotify was unreachable
+    pub fn queue_action(&self, action: QueuedAction) -> Result<PendingAction> {
+        let (track_info, seen_at) = self.last_track.as_ref()
+            .ok_or_else(|| anyhow!("No known track to {}", action))?;
+        if seen_at.elapsed() > LAST_TRACK_MAX_AGE {
+            return Err(anyhow!(
+                "Spotify has been unreachable for {}s, the playing track may have changed since",
+                seen_at.elapsed().as_secs()
+            ));
         }
+        let track_id = track_info.id.as_deref()
+            .ok_or_else(|| anyhow!("Current track has no ID"))?;
+        let track_id = self.parse_track_id(track_id)?;
         
-        Ok(())
+        let pending = PendingAction {
+            track_id: track_id.id().to_string(),
+            action,
+            queued_at: chrono::Utc::now(),
+            name: track_info.name.clone(),
+            artist: track_info.artist.clone(),
+        };
+        self.action_queue.push(pending.clone())?;
+        info!("📥 Queued {} of {} - {} until Spotify is reachable", action, pending.name, pending.artist);
+        
+        Ok(pending)
+    }
+    
+    /// What toggling the last known track queues while offline
+    ///
+    /// Spotify can't be asked, so this flips the action already queued for the track,
+    /// or else its cached liked status.
+    pub fn offline_toggle_action(&self) -> Result<QueuedAction> {
+        let (track_info, _) = self.last_track.as_ref()
+            .ok_or_else(|| anyhow!("No known track to toggle"))?;
+        let track_id_str = track_info.id.as_deref()
+            .ok_or_else(|| anyhow!("Current track has no ID"))?;
+        let track_id = self.parse_track_id(track_id_str)?;
+        
+        let liked = match self.action_queue.pending_for(track_id.id()) {
+            Some(queued) => queued == QueuedAction::Like,
+            None => self.cached_track_liked(track_id_str)
+                .ok_or_else(|| anyhow!("Whether {} - {} is liked is unknown while offline", track_info.name, track_info.artist))?,
+        };
+        
+        Ok(if liked { QueuedAction::Unlike } else { QueuedAction::Like })
+    }
+    
+    /// Apply a queued action, see `action_queue::replay`
+    pub async fn apply_queued(&mut self, pending: &PendingAction) -> Result<TrackInfo> {
+        match pending.action {
+            QueuedAction::Like => self.like_track(pending.track_info()).await,
+            QueuedAction::Unlike => self.unlike_track(pending.track_info()).await,
+        }
+    }
+    
+    /// A queued action for a track is superseded once a like or unlike of it went through
+    fn discard_queued(&self, track_id: &TrackId<'_>) {
+        if let Err(e) = self.action_queue.discard(track_id.id()) {
+            warn!("⚠️ Failed to update offline action queue: {}", e);
+        }
     }
-}
\ No newline at end of file
+    
+    /// Write a verified like or unlike through to the library, until the next sync fills in the details
+    fn record_in_library(&self, track_id: &TrackId<'_>, track_info: &TrackInfo, liked: bool) {
+        let Some(library) = &self.library else {
+            return;
+        };
+        
+        let result = if liked {
+            library.upsert(&[LibraryTrack {
+                id: track_id.id().to_string(),
+                name: track_info.name.clone(),
+                artists: track_info.artist.clone(),
+                album: String::new(),
+                duration_ms: 0,
+                added_at: chrono::Utc::now(),
+            }]).map(|_| ())
+        } else {
+            library.remove(track_id.id())
+        };
+        
+        if let Err(e) = result {
+            warn!("⚠️ Failed to update Liked Songs library: {}", e);
+        }
+    }
+    
+    /// Like current track with verification
+    pub async fn like_current_track(&mut self) -> Result<TrackInfo> {
+        self.require_scopes(Action::LikeTrack).await?;
+        
+        let track_info = self.get_current_track().await?;
+        self.like_track(track_info).await
+    }
+    
+    /// Like the current track without waiting for verification, see `verify_pending`
+    pub async fn like_current_track_optimistic(&mut self) -> Result<PendingVerification> {
+        self.require_scopes(Action::LikeTrack).await?;
+        
+        let track_info = self.get_current_track().await?;
+        self.write_track(track_info, true).await
+    }
+    
+    /// Like a specific track with verification
+    pub async fn like_track(&mut self, track_info: TrackInfo) -> Result<TrackInfo> {
+        let pending = self.write_track(track_info, true).await?;
+        let track_info = pending.track_info.clone();
+        
+        // Verify the operation with retries
+        let Some(verification_result) = self.verify_write(pending).await else {
+            // Liked or unliked again meanwhile, the newer write is the one to verify
+            return Ok(track_info);
+        };
+        
+        if verification_result.success {
+            info!("✅ Successfully liked and verified: {} - {} (verified in {}ms after {} attempts)", 
+                track_info.name, track_info.artist, 
+                verification_result.verified_after_ms,
+                verification_result.attempts);
+            Ok(track_info)
+        } else {
+            error!("❌ Failed to verify track was liked: {} - {}", track_info.name, track_info.artist);
+            Err(anyhow!("Track like operation failed verification - the track may not have been saved to your library"))
+        }
+    }
+    
+    /// Save current track (alias for like_current_track_optimistic for compatibility)
+    pub async fn save_current_track(&mut self) -> Result<PendingVerification> {
+        self.like_current_track_optimistic().await
+    }
+    
+    /// Unlike current track with verification
+    pub async fn unlike_current_track(&mut self) -> Result<TrackInfo> {
+        self.require_scopes(Action::UnlikeTrack).await?;
+        
+        let track_info = self.get_current_track().await?;
+        self.unlike_track(track_info).await
+    }
+    
+    /// Unlike the current track without waiting for verification, see `verify_pending`
+    pub async fn unlike_current_track_optimistic(&mut self) -> Result<PendingVerification> {
+        self.require_scopes(Action::UnlikeTrack).await?;
+        
+        let track_info = self.get_current_track().await?;
+        self.write_track(track_info, false).await
+    }
+    
+    /// Unlike a specific track with verification
+    pub async fn unlike_track(&mut self, track_info: TrackInfo) -> Result<TrackInfo> {
+        let pending = self.write_track(track_info, false).await?;
+        let track_info = pending.track_info.clone();
+        
+        // Verify the operation with retries
+        let Some(verification_result) = self.verify_write(pending).await else {
+            // Liked or unliked again meanwhile, the newer write is the one to verify
+            return Ok(track_info);
+        };
+        
+        if verification_result.success {
+            info!("✅ Successfully unliked and verified: {} - {} (verified in {}ms after {} attempts)", 
+                track_info.name, track_info.artist, 
+                verification_result.verified_after_ms,
+                verification_result.attempts);
+            Ok(track_info)
+        } else {
+            error!("❌ Failed to verify track was unliked: {} - {}", track_info.name, track_info.artist);
+            Err(anyhow!("Track unlike operation failed verification - the track may still be in your library"))
+        }
+    }
+    
+    /// Flip the current track's saved state, without waiting for verification
+    ///
+    /// `PendingVerification::liked` is the state the track was switched to.
+    pub async fn toggle_like_current_track(&mut self) -> Result<PendingVerification> {
+        let track_info = self.get_current_track().await?;
+        let track_id = match &track_info.id {
+            Some(track_id_str) => self.parse_track_id(track_id_str)?.into_static(),
+            None => return Err(anyhow!("Current track has no ID")),
+        };
+        
+        // Read fresh: a cache entry may be minutes old, and flipping that could undo a change made elsewhere
+        let liked = self.fetch_track_liked(&track_id).await?;
+        info!("🔁 Toggling {} - {}: currently {}", track_info.name, track_info.artist, if liked { "liked" } else { "not liked" });
+        self.write_track(track_info, !liked).await
+    }
+    
+    /// Add or delete a single track, leaving verification to the caller
+    async fn write_track(&mut self, track_info: TrackInfo, liked: bool) -> Result<PendingVerification> {
+        let (action, operation) = if liked { (Action::LikeTrack, "LIKE") } else { (Action::UnlikeTrack, "UNLIKE") };
+        self.require_scopes(action).await?;
+        
+        let track_id = match &track_info.id {
+            Some(track_id_str) => self.parse_track_id(track_id_str)?.into_static(),
+            None => return Err(anyhow!("Current track has no ID")),
+        };
+        
+        info!("🎯 Attempting to {} track: {} - {} (ID: {})", operation, track_info.name, track_info.artist, track_id.id());
+        
+        let started = std::time::Instant::now();
+        let written = if liked {
+            authorized!(self, client => client.saved_tracks_add(slice::from_ref(&track_id)).await)
+                .context("Failed to add track to saved tracks")
+        } else {
+            authorized!(self, client => client.saved_tracks_delete(slice::from_ref(&track_id)).await)
+                .context("Failed to remove track from saved tracks")
+        };
+        self.stats.record_api_call(&operation.to_lowercase(), started.elapsed().as_millis() as u64, written.is_ok());
+        written?;
+        self.liked_cache.set(track_id.id(), liked);
+        
+        info!("📡 {} API call completed, starting verification...", operation);
+        
+        Ok(self.note_write(track_info, track_id, liked))
+    }
+    
+    /// Remember a like or unlike as the latest intent for the track, to be verified
+    fn note_write(&mut self, track_info: TrackInfo, track_id: TrackId<'static>, liked: bool) -> PendingVerification {
+        self.write_generation += 1;
+        self.latest_writes.insert(track_id.id().to_string(), self.write_generation);
+        
+        PendingVerification {
+            track_info,
+            track_id,
+            liked,
+            liked_cache: Arc::clone(&self.liked_cache),
+            generation: self.write_generation,
+        }
+    }
+    
+    /// Whether no like or unlike of the track went through after `pending`
+    fn is_latest_write(&self, pending: &PendingVerification) -> bool {
+        self.latest_writes.get(pending.track_id.id()) == Some(&pending.generation)
+    }
+    
+    /// Bookkeeping once a like or unlike has been read back from Spotify
+    fn finish_verified(&self, pending: &PendingVerification) {
+        self.record_in_library(&pending.track_id, &pending.track_info, pending.liked);
+        self.discard_queued(&pending.track_id);
+    }
+    
+    /// Verify an optimistic like or unlike, locking the manager only for each read-back
+    ///
+    /// Waits between attempts without the lock, so the poller and other actions
+    /// keep going. Returns `None` if the profile was switched in the meantime, or
+    /// the track was liked or unliked again: re-issuing this write would undo that.
+    pub async fn verify_pending(manager: &Mutex<Self>, pending: PendingVerification) -> Option<VerificationResult> {
+        Self::verify_writes(manager, vec![pending]).await.pop().flatten()
+    }
+    
+    /// Verify a write holding on to the manager throughout, see `verify_pending` for sharing it
+    async fn verify_write(&mut self, pending: PendingVerification) -> Option<VerificationResult> {
+        Self::verify_writes(&Mutex::new(self), vec![pending]).await.pop().flatten()
+    }
+    
+    /// Read likes or unlikes (all the same way) back until Spotify shows them, see `convergence::converge`
+    ///
+    /// The manager is locked only for each read and re-write. Writes that show up
+    /// drop out of later reads, the rest are written again on the last attempts.
+    /// A write of a track liked or unliked again since ends without a verdict
+    /// (`None`), as do all of them after a profile switch.
+    async fn verify_writes<M: AsMut<Self> + Send>(manager: &Mutex<M>, writes: Vec<PendingVerification>) -> Vec<Option<VerificationResult>> {
+        let (verification, stats) = {
+            let mut manager = manager.lock().await;
+            let manager = manager.as_mut();
+            (manager.verification.clone(), manager.stats())
+        };
+        if !verification.enabled {
+            return writes.into_iter().map(|pending| Some(VerificationResult::unverified(pending.track_info))).collect();
+        }
+        let Some(liked) = writes.first().map(|pending| pending.liked) else {
+            return Vec::new();
+        };
+        let operation = if liked { "LIKE" } else { "UNLIKE" };
+        
+        let start_time = std::time::Instant::now();
+        let progress = Arc::new(std::sync::Mutex::new(WriteProgress {
+            results: vec![None; writes.len()],
+            pending: (0..writes.len()).collect(),
+            writes,
+            reads: 0,
+        }));
+        let probe_progress = Arc::clone(&progress);
+        let reapply_progress = Arc::clone(&progress);
+        
+        let outcome = convergence::converge(
+            manager,
+            &verification,
+            operation,
+            true,
+            (),
+            move |manager: &mut M| -> Step<'_, bool> {
+                let progress = Arc::clone(&probe_progress);
+                Box::pin(async move {
+                    let manager = manager.as_mut();
+                    let track_ids = progress.lock().unwrap().start_read(manager)?;
+                    if track_ids.is_empty() {
+                        return Ok(true);
+                    }
+                    
+                    let statuses = manager.read_back(&track_ids).await?;
+                    let verified_after_ms = start_time.elapsed().as_millis() as u64;
+                    Ok(progress.lock().unwrap().finish_read(manager, &statuses, verified_after_ms))
+                })
+            },
+            Some(convergence::step(move |manager: &mut M| -> Step<'_, ()> {
+                let progress = Arc::clone(&reapply_progress);
+                Box::pin(async move {
+                    let manager = manager.as_mut();
+                    let track_ids = progress.lock().unwrap().pending_ids();
+                    
+                    manager.priority = Priority::Background;
+                    let written = manager.write_batch(&track_ids, liked).await;
+                    manager.priority = Priority::User;
+                    written
+                })
+            })),
+        ).await;
+        
+        let mut progress = progress.lock().unwrap();
+        let WriteProgress { writes, results, pending, .. } = &mut *progress;
+        let Some(outcome) = outcome else {
+            return vec![None; writes.len()];
+        };
+        for &i in pending.iter() {
+            results[i] = Some(VerificationResult {
+                success: false,
+                subject: writes[i].track_info.clone(),
+                verified_after_ms: outcome.verified_after_ms,
+                attempts: outcome.attempts,
+            });
+        }
+        for result in results.iter().flatten() {
+            stats.record_verification(&operation.to_lowercase(), result);
+        }
+        std::mem::take(results)
+    }
+    
+    /// Liked status of tracks being verified, read with background priority
+    async fn read_back(&mut self, track_ids: &[TrackId<'_>]) -> Result<Vec<bool>> {
+        self.priority = Priority::Background;
+        let result = async {
+            self.require_scopes(Action::CheckLiked).await?;
+            
+            let started = std::time::Instant::now();
+            let liked = self.fetch_tracks_liked(track_ids).await;
+            self.stats.record_api_call("verify_liked", started.elapsed().as_millis() as u64, liked.is_ok());
+            liked
+        }.await;
+        self.priority = Priority::User;
+        result
+    }
+    
+    /// Like any number of tracks (IDs, URIs or URLs), verifying the whole batch
+    pub async fn like_tracks<S: AsRef<str>>(manager: &Mutex<Self>, track_ids: &[S]) -> Result<Vec<VerificationResult>> {
+        Self::apply_batch(manager, track_ids, true).await
+    }
+    
+    /// Unlike any number of tracks (IDs, URIs or URLs), verifying the whole batch
+    pub async fn unlike_tracks<S: AsRef<str>>(manager: &Mutex<Self>, track_ids: &[S]) -> Result<Vec<VerificationResult>> {
+        Self::apply_batch(manager, track_ids, false).await
+    }
+    
+    /// Write the tracks with `write_tracks`, then verify them together like `verify_pending` does
+    async fn apply_batch<S: AsRef<str>>(manager: &Mutex<Self>, track_ids: &[S], liked: bool) -> Result<Vec<VerificationResult>> {
+        let writes = manager.lock().await.write_tracks(track_ids, liked).await?;
+        let track_infos: Vec<TrackInfo> = writes.iter().map(|pending| pending.track_info.clone()).collect();
+        
+        let operation = if liked { "LIKE" } else { "UNLIKE" };
+        info!("📡 {} API calls completed, starting batch verification...", operation);
+        let results: Vec<VerificationResult> = Self::verify_writes(manager, writes).await
+            .into_iter()
+            .zip(track_infos)
+            .map(|(result, track_info)| result.unwrap_or_else(|| VerificationResult::unverified(track_info)))
+            .collect();
+        
+        let verified = results.iter().filter(|result| result.success).count();
+        if verified == results.len() {
+            info!("✅ {} verified for all {} tracks", operation, verified);
+        } else {
+            error!("❌ {} verified for {} of {} tracks", operation, verified, results.len());
+        }
+        
+        Ok(results)
+    }
+    
+    /// Add or delete the tracks in chunks of `BATCH_SIZE`, leaving verification to the caller
+    async fn write_tracks<S: AsRef<str>>(&mut self, track_ids: &[S], liked: bool) -> Result<Vec<PendingVerification>> {
+        self.require_scopes(if liked { Action::LikeTrack } else { Action::UnlikeTrack }).await?;
+        
+        let mut seen = HashSet::new();
+        let mut ids: Vec<TrackId<'static>> = Vec::with_capacity(track_ids.len());
+        for track_id_str in track_ids {
+            let track_id = self.parse_track_id(track_id_str.as_ref())
+                .with_context(|| format!("Invalid track ID: {}", track_id_str.as_ref()))?
+                .into_static();
+            if seen.insert(track_id.id().to_string()) {
+                ids.push(track_id);
+            }
+        }
+        
+        let operation = if liked { "LIKE" } else { "UNLIKE" };
+        info!("🎯 Attempting to {} {} tracks", operation, ids.len());
+        
+        self.write_batch(&ids, liked).await?;
+        Ok(ids.into_iter()
+            .map(|track_id| {
+                self.liked_cache.set(track_id.id(), liked);
+                self.note_write(TrackInfo::from_track_id(&track_id), track_id, liked)
+            })
+            .collect())
+    }
+    
+    /// Add or delete tracks, one request per `BATCH_SIZE` IDs
+    async fn write_batch(&mut self, track_ids: &[TrackId<'_>], liked: bool) -> Result<()> {
+        for chunk in track_ids.chunks(BATCH_SIZE) {
+            if liked {
+                authorized!(self, client => client.saved_tracks_add(chunk).await)
+                    .context("Failed to add tracks to saved tracks")?;
+            } else {
+                authorized!(self, client => client.saved_tracks_delete(chunk).await)
+                    .context("Failed to remove tracks from saved tracks")?;
+            }
+        }
+        Ok(())
+    }
+    
+    /// Liked status of many tracks, one `contains` request per `BATCH_SIZE` IDs, bypassing the cache
+    async fn fetch_tracks_liked(&mut self, track_ids: &[TrackId<'_>]) -> Result<Vec<bool>> {
+        let mut liked = Vec::with_capacity(track_ids.len());
+        for chunk in track_ids.chunks(BATCH_SIZE) {
+            let saved = authorized!(self, client => client.saved_tracks_contains(chunk).await)
+                .context("Failed to check if tracks are saved")?;
+            if saved.len() != chunk.len() {
+                return Err(anyhow!("Spotify answered {} liked statuses for {} tracks", saved.len(), chunk.len()));
+            }
+            liked.extend(saved);
+        }
+        
+        for (track_id, liked) in track_ids.iter().zip(&liked) {
+            self.liked_cache.set(track_id.id(), *liked);
+        }
+        Ok(liked)
+    }
+    
+    /// Check if a track is currently liked, answered from the liked cache when possible
+    pub async fn is_track_liked(&mut self, track_id: &TrackId<'_>) -> Result<bool> {
+        if let Some(liked) = self.liked_cache.get(track_id.id()) {
+            return Ok(liked);
+        }
+        
+        self.fetch_track_liked(track_id).await
+    }
+    
+    /// Liked status of the track from the cache or the library, without an API call
+    pub fn cached_track_liked(&self, track_id_str: &str) -> Option<bool> {
+        let track_id = self.parse_track_id(track_id_str).ok()?;
+        self.liked_cache.get(track_id.id()).or_else(|| {
+            self.library.as_ref()?
+                .is_liked(track_id.id())
+                .map_err(|e| warn!("⚠️ Liked Songs library lookup failed: {}", e))
+                .ok()?
+        })
+    }
+    
+    /// Background variant of `is_track_liked` for the poller, `None` if it couldn't be determined
+    pub async fn poll_track_liked(&mut self, track_id_str: &str) -> Option<bool> {
+        let track_id = self.parse_track_id(track_id_str).ok()?;
+        
+        self.priority = Priority::Background;
+        let result = self.is_track_liked(&track_id).await;
+        self.priority = Priority::User;
+        
+        result.map_err(|e| warn!("⚠️ Could not check liked status: {}", e)).ok()
+    }
+    
+    /// Ask Spotify whether a track is liked, bypassing (and updating) the cache
+    async fn fetch_track_liked(&mut self, track_id: &TrackId<'_>) -> Result<bool> {
+        self.require_scopes(Action::CheckLiked).await?;
+        
+        info!("🔍 Checking if track is liked: {}", track_id.id());
+        
+        let started = std::time::Instant::now();
+        let is_saved = authorized!(self, client => client.saved_tracks_contains(slice::from_ref(track_id)).await)
+            .context("Failed to check if track is saved");
+        self.stats.record_api_call("check_liked", started.elapsed().as_millis() as u64, is_saved.is_ok());
+        let is_saved = is_saved?;
+        
+        let result = is_saved.first() == Some(&true);
+        self.liked_cache.set(track_id.id(), result);
+        info!("🔍 Track liked status: {} = {}", track_id.id(), result);
+        
+        Ok(result)
+    }
+    
+    /// Parse various track ID formats
+    fn parse_track_id<'a>(&self, track_id_str: &'a str) -> Result<TrackId<'a>> {
+        // Handle different track ID formats
+        if track_id_str.starts_with("spotify:track:") {
+            TrackId::from_uri(track_id_str)
+                .context("Failed to parse Spotify track URI")
+        } else if track_id_str.len() == 22 && track_id_str.chars().all(|c| c.is_alphanumeric()) {
+            TrackId::from_id(track_id_str)
+                .context("Failed to parse raw Spotify track ID")
+        } else {
+            // Try to extract ID from URL or other formats
+            let clean_id = track_id_str
+                .split('/')
+                .last()
+                .unwrap_or(track_id_str)
+                .split('?')
+                .next()
+                .unwrap_or(track_id_str);
+            
+            TrackId::from_id(clean_id)
+                .context("Failed to parse track ID from URL format")
+        }
+    }
+    
+    /// Get current user info (useful for testing authentication)
+    pub async fn get_current_user(&mut self) -> Result<rspotify::model::PrivateUser> {
+        self.require_scopes(Action::ReadProfile).await?;
+        Ok(authorized!(self, client => client.current_user().await)?)
+    }
+    
+    /// Force a token refresh (useful for testing)
+    pub async fn refresh_token(&mut self) -> Result<()> {
+        // Persisted by the token callback
+        self.client.refresh_token().await?;
+        info!("✅ Token manually refreshed");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spotify_api::fake::FakeSpotifyApi;
+    use rspotify::model::RepeatState;
+
+    const TRACK_ID: &str = "4uLU6hMCjMI75M1A2tKUQC";
+
+    fn manager(api: FakeSpotifyApi, max_verification_attempts: u32) -> SpotifyManager<FakeSpotifyApi> {
+        SpotifyManager::with_api(api, VerificationConfig::for_tests(max_verification_attempts))
+    }
+
+    fn playing() -> FakeSpotifyApi {
+        FakeSpotifyApi::new().playing(TRACK_ID, "Never Gonna Give You Up", "Rick Astley")
+    }
+
+    fn track_info() -> TrackInfo {
+        TrackInfo {
+            id: Some(format!("spotify:track:{}", TRACK_ID)),
+            name: "Never Gonna Give You Up".to_string(),
+            artist: "Rick Astley".to_string(),
+            uri: Some(format!("spotify:track:{}", TRACK_ID)),
+        }
+    }
+
+    /// Verify a write of the track against whatever state the fake is in
+    async fn verify_written(manager: &mut SpotifyManager<FakeSpotifyApi>, liked: bool) -> VerificationResult {
+        let pending = manager.note_write(track_info(), TrackId::from_id(TRACK_ID).unwrap(), liked);
+        manager.verify_write(pending).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn like_current_track_saves_and_verifies() {
+        let mut manager = manager(playing(), 3);
+
+        let track = manager.like_current_track().await.unwrap();
+
+        assert_eq!(track.id, Some(format!("spotify:track:{}", TRACK_ID)));
+        assert_eq!(track.artist, "Rick Astley");
+        assert!(manager.client.is_liked(TRACK_ID));
+        assert_eq!(manager.client.add_calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn like_current_track_waits_out_read_lag() {
+        let mut manager = manager(playing().with_read_lag(2), 5);
+
+        manager.like_current_track().await.unwrap();
+
+        assert_eq!(manager.client.add_calls(), 1);
+        assert_eq!(manager.client.contains_calls(), 3);
+    }
+
+    #[tokio::test]
+    async fn like_current_track_reissues_dropped_write() {
+        let mut manager = manager(playing().dropping_writes(1), 3);
+
+        manager.like_current_track().await.unwrap();
+
+        assert!(manager.client.is_liked(TRACK_ID));
+        assert_eq!(manager.client.add_calls(), 2);
+    }
+
+    #[tokio::test]
+    async fn like_current_track_fails_when_never_saved() {
+        let mut manager = manager(playing().dropping_writes(u32::MAX), 3);
+
+        let error = manager.like_current_track().await.unwrap_err();
+
+        assert!(error.to_string().contains("failed verification"));
+        assert!(!manager.client.is_liked(TRACK_ID));
+    }
+
+    #[tokio::test]
+    async fn like_current_track_requires_playback() {
+        let mut manager = manager(FakeSpotifyApi::new(), 3);
+
+        assert!(manager.like_current_track().await.is_err());
+        assert_eq!(manager.client.add_calls(), 0);
+    }
+
+    #[tokio::test]
+    async fn unlike_current_track_removes_and_verifies() {
+        let mut manager = manager(playing().with_liked(TRACK_ID), 3);
+
+        manager.unlike_current_track().await.unwrap();
+
+        assert!(!manager.client.is_liked(TRACK_ID));
+        assert_eq!(manager.client.delete_calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn unlike_current_track_reissues_dropped_write() {
+        let mut manager = manager(playing().with_liked(TRACK_ID).dropping_writes(1), 3);
+
+        manager.unlike_current_track().await.unwrap();
+
+        assert!(!manager.client.is_liked(TRACK_ID));
+        assert_eq!(manager.client.delete_calls(), 2);
+    }
+
+    #[tokio::test]
+    async fn unlike_current_track_fails_when_never_removed() {
+        let mut manager = manager(playing().with_liked(TRACK_ID).dropping_writes(u32::MAX), 3);
+
+        let error = manager.unlike_current_track().await.unwrap_err();
+
+        assert!(error.to_string().contains("failed verification"));
+        assert!(manager.client.is_liked(TRACK_ID));
+    }
+
+    #[tokio::test]
+    async fn is_track_liked_is_answered_from_cache_after_like() {
+        let mut manager = manager(playing(), 3);
+        manager.like_current_track().await.unwrap();
+        let contains_calls = manager.client.contains_calls();
+
+        let track_id = TrackId::from_id(TRACK_ID).unwrap();
+        assert!(manager.is_track_liked(&track_id).await.unwrap());
+
+        assert_eq!(manager.client.contains_calls(), contains_calls);
+        assert_eq!(manager.cached_track_liked(TRACK_ID), Some(true));
+    }
+
+    #[tokio::test]
+    async fn is_track_liked_caches_spotify_answer() {
+        let mut manager = manager(FakeSpotifyApi::new().with_liked(TRACK_ID), 3);
+        let track_id = TrackId::from_id(TRACK_ID).unwrap();
+
+        assert!(manager.is_track_liked(&track_id).await.unwrap());
+        assert!(manager.is_track_liked(&track_id).await.unwrap());
+
+        assert_eq!(manager.client.contains_calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn verification_reads_back_instead_of_trusting_cache() {
+        let mut manager = manager(FakeSpotifyApi::new().dropping_writes(u32::MAX), 2);
+        manager.liked_cache.set(TRACK_ID, true);
+
+        let result = verify_written(&mut manager, true).await;
+
+        assert!(!result.success);
+        assert_eq!(manager.cached_track_liked(TRACK_ID), Some(false));
+    }
+
+    #[tokio::test]
+    async fn verify_like_succeeds_on_the_first_read_when_already_liked() {
+        let mut manager = manager(FakeSpotifyApi::new().with_liked(TRACK_ID), 3);
+
+        let result = verify_written(&mut manager, true).await;
+
+        assert!(result.success);
+        assert_eq!(result.attempts, 1);
+        assert_eq!(manager.client.add_calls(), 0);
+    }
+
+    #[tokio::test]
+    async fn verify_like_retries_failed_reads() {
+        let mut manager = manager(FakeSpotifyApi::new().with_liked(TRACK_ID).failing_reads(1), 3);
+
+        let result = verify_written(&mut manager, true).await;
+
+        assert!(result.success);
+        assert_eq!(result.attempts, 2);
+    }
+
+    #[tokio::test]
+    async fn verify_like_reports_failure_after_all_attempts() {
+        let mut manager = manager(FakeSpotifyApi::new().dropping_writes(u32::MAX), 2);
+
+        let result = verify_written(&mut manager, true).await;
+
+        assert!(!result.success);
+        assert_eq!(result.attempts, 3);
+        // Both attempts fall within the re-issue window
+        assert_eq!(manager.client.add_calls(), 2);
+    }
+
+    #[tokio::test]
+    async fn verify_unlike_succeeds_on_the_first_read_when_not_liked() {
+        let mut manager = manager(FakeSpotifyApi::new(), 3);
+
+        let result = verify_written(&mut manager, false).await;
+
+        assert!(result.success);
+        assert_eq!(result.attempts, 1);
+        assert_eq!(manager.client.delete_calls(), 0);
+    }
+
+    #[tokio::test]
+    async fn verify_unlike_counts_attempts_until_removed() {
+        let mut manager = manager(FakeSpotifyApi::new().with_liked(TRACK_ID).with_read_lag(2), 5);
+        let track_id = TrackId::from_id(TRACK_ID).unwrap();
+        manager.client.saved_tracks_delete(slice::from_ref(&track_id)).await.unwrap();
+
+        let result = verify_written(&mut manager, false).await;
+
+        assert!(result.success);
+        assert_eq!(result.attempts, 3);
+    }
+
+    #[tokio::test]
+    async fn verify_unlike_reports_failure_after_all_attempts() {
+        let mut manager = manager(FakeSpotifyApi::new().with_liked(TRACK_ID).dropping_writes(u32::MAX), 2);
+
+        let result = verify_written(&mut manager, false).await;
+
+        assert!(!result.success);
+        assert_eq!(result.attempts, 3);
+        assert_eq!(manager.client.delete_calls(), 2);
+    }
+
+    fn track_ids(count: usize) -> Vec<String> {
+        (0..count).map(|i| format!("{:0>22}", i)).collect()
+    }
+
+    #[tokio::test]
+    async fn like_tracks_chunks_requests_and_verifies_in_batches() {
+        let manager = Mutex::new(manager(FakeSpotifyApi::new(), 3));
+        let ids = track_ids(120);
+
+        let results = SpotifyManager::like_tracks(&manager, &ids).await.unwrap();
+
+        assert_eq!(results.len(), 120);
+        assert!(results.iter().all(|result| result.success && result.attempts == 1));
+        assert_eq!(results[7].subject.id, Some(format!("spotify:track:{}", ids[7])));
+        assert_eq!(manager.lock().await.client.add_calls(), 3);
+        assert_eq!(manager.lock().await.client.contains_calls(), 3);
+        assert_eq!(manager.lock().await.client.largest_request(), BATCH_SIZE);
+    }
+
+    #[tokio::test]
+    async fn like_tracks_accepts_uris_and_urls_and_skips_duplicates() {
+        let manager = Mutex::new(manager(FakeSpotifyApi::new(), 3));
+        let inputs = [
+            TRACK_ID.to_string(),
+            format!("spotify:track:{}", TRACK_ID),
+            format!("https://open.spotify.com/track/{}?si=abc", TRACK_ID),
+        ];
+
+        let results = SpotifyManager::like_tracks(&manager, &inputs).await.unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert!(manager.lock().await.client.is_liked(TRACK_ID));
+    }
+
+    #[tokio::test]
+    async fn like_tracks_rejects_invalid_ids_before_writing() {
+        let manager = Mutex::new(manager(FakeSpotifyApi::new(), 3));
+
+        let error = SpotifyManager::like_tracks(&manager, &[TRACK_ID, "not a track!"]).await.unwrap_err();
+
+        assert!(error.to_string().contains("not a track!"));
+        assert_eq!(manager.lock().await.client.add_calls(), 0);
+    }
+
+    #[tokio::test]
+    async fn unlike_tracks_reports_each_track() {
+        let ids = track_ids(3);
+        let api = FakeSpotifyApi::new()
+            .with_liked(&ids[0])
+            .with_liked(&ids[1])
+            .with_liked(&ids[2])
+            .ignoring_writes_for(&ids[1])
+            .with_read_lag(1);
+        let manager = Mutex::new(manager(api, 3));
+
+        let results = SpotifyManager::unlike_tracks(&manager, &ids).await.unwrap();
+
+        let outcome: Vec<(bool, u32)> = results.iter().map(|result| (result.success, result.attempts)).collect();
+        assert_eq!(outcome, [(true, 2), (false, 4), (true, 2)]);
+        // Only the stuck track is checked again, and re-deleted on the last attempts
+        assert_eq!(manager.lock().await.client.contains_calls(), 4);
+        assert_eq!(manager.lock().await.client.delete_calls(), 4);
+    }
+
+    #[tokio::test]
+    async fn verified_batch_likes_reach_the_library() {
+        let library = Arc::new(Library::open_in_memory().unwrap());
+        let mut manager = manager(FakeSpotifyApi::new().with_read_lag(1), 3);
+        manager.attach_library(Arc::clone(&library));
+        let manager = Mutex::new(manager);
+        let ids = track_ids(2);
+
+        SpotifyManager::like_tracks(&manager, &ids).await.unwrap();
+        assert_eq!(library.tracks().unwrap().len(), 2);
+
+        SpotifyManager::unlike_tracks(&manager, &ids).await.unwrap();
+        assert!(library.tracks().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn disabled_verification_trusts_the_write() {
+        let verification = VerificationConfig { enabled: false, ..VerificationConfig::for_tests(3) };
+        let mut manager = SpotifyManager::with_api(playing(), verification);
+
+        manager.like_current_track().await.unwrap();
+
+        assert_eq!(manager.client.add_calls(), 1);
+        assert_eq!(manager.client.contains_calls(), 0);
+    }
+
+    #[tokio::test]
+    async fn verification_without_reissue_never_repeats_the_write() {
+        let verification = VerificationConfig { reissue: false, ..VerificationConfig::for_tests(3) };
+        let mut manager = SpotifyManager::with_api(playing().dropping_writes(1), verification);
+
+        assert!(manager.like_current_track().await.is_err());
+        assert_eq!(manager.client.add_calls(), 1);
+        assert_eq!(manager.client.contains_calls(), 4);
+    }
+
+    #[tokio::test]
+    async fn optimistic_like_returns_before_verifying() {
+        let mut manager = manager(playing().with_read_lag(2), 5);
+
+        let pending = manager.like_current_track_optimistic().await.unwrap();
+        assert_eq!(manager.client.add_calls(), 1);
+        assert_eq!(manager.client.contains_calls(), 0);
+
+        let manager = Mutex::new(manager);
+        let result = SpotifyManager::verify_pending(&manager, pending).await.unwrap();
+
+        assert!(result.success);
+        assert_eq!(result.attempts, 3);
+        assert_eq!(manager.lock().await.client.contains_calls(), 3);
+    }
+
+    #[tokio::test]
+    async fn verify_pending_reports_an_unlike_that_never_sticks() {
+        let mut manager = manager(playing().with_liked(TRACK_ID).ignoring_writes_for(TRACK_ID), 3);
+
+        let pending = manager.unlike_current_track_optimistic().await.unwrap();
+        let manager = Mutex::new(manager);
+        let result = SpotifyManager::verify_pending(&manager, pending).await.unwrap();
+
+        assert!(!result.success);
+        assert_eq!(result.attempts, 4);
+        assert!(manager.lock().await.client.is_liked(TRACK_ID));
+    }
+
+    #[tokio::test]
+    async fn verify_pending_drops_out_after_a_profile_switch() {
+        let mut first = manager(playing().dropping_writes(1), 3);
+        let pending = first.like_current_track_optimistic().await.unwrap();
+
+        let manager = Mutex::new(manager(playing(), 3));
+        assert!(SpotifyManager::verify_pending(&manager, pending).await.is_none());
+        assert_eq!(manager.lock().await.client.add_calls(), 0);
+    }
+
+    #[tokio::test]
+    async fn verify_pending_never_reissues_a_reversed_write() {
+        let mut manager = manager(playing().with_read_lag(2), 3);
+        let like = manager.like_current_track_optimistic().await.unwrap();
+        let unlike = manager.unlike_current_track_optimistic().await.unwrap();
+
+        let manager = Mutex::new(manager);
+        assert!(SpotifyManager::verify_pending(&manager, like).await.is_none());
+        assert!(SpotifyManager::verify_pending(&manager, unlike).await.unwrap().success);
+
+        let manager = manager.lock().await;
+        assert!(!manager.client.is_liked(TRACK_ID));
+        assert_eq!(manager.client.add_calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn like_records_write_latency_and_verification() {
+        let mut manager = manager(playing().with_read_lag(1), 3);
+
+        manager.like_current_track().await.unwrap();
+
+        let summary = manager.stats().summary();
+        assert_eq!(summary.latency["like"].calls, 1);
+        // Read-backs are kept apart from the lookups the tray makes
+        assert_eq!(summary.latency["verify_liked"].calls, 2);
+        assert!(!summary.latency.contains_key("check_liked"));
+        assert_eq!(summary.verifications, 1);
+        assert_eq!(summary.average_attempts, 2.0);
+    }
+
+    #[tokio::test]
+    async fn toggle_like_flips_the_saved_state() {
+        let mut manager = manager(playing(), 3);
+
+        let pending = manager.toggle_like_current_track().await.unwrap();
+        assert!(pending.liked);
+        assert!(manager.client.is_liked(TRACK_ID));
+
+        let pending = manager.toggle_like_current_track().await.unwrap();
+        assert!(!pending.liked);
+        assert!(!manager.client.is_liked(TRACK_ID));
+        // Each toggle reads the saved state instead of trusting the cache
+        assert_eq!(manager.client.contains_calls(), 2);
+    }
+
+    #[tokio::test]
+    async fn toggle_like_ignores_a_stale_cache_entry() {
+        let mut manager = manager(playing(), 3);
+        // Unliked in another app since the cache was filled
+        manager.liked_cache.set(TRACK_ID, true);
+
+        let pending = manager.toggle_like_current_track().await.unwrap();
+
+        assert!(pending.liked);
+        assert!(manager.client.is_liked(TRACK_ID));
+    }
+
+    #[tokio::test]
+    async fn background_calls_never_ask_for_missing_scopes() {
+        let without_library_read: Vec<String> = permissions::all_scopes().into_iter()
+            .filter(|scope| scope != "user-library-read")
+            .collect();
+        let mut manager = manager(playing().with_scopes(&without_library_read), 3);
+
+        assert_eq!(manager.poll_track_liked(TRACK_ID).await, None);
+        assert_eq!(manager.client.reauthorizations(), 0);
+        assert_eq!(manager.client.contains_calls(), 0);
+
+        // The user wasn't asked, so a user action still is, without the manager locked
+        let error = manager.toggle_like_current_track().await.err().unwrap();
+        assert!(error.downcast_ref::<ConsentNeeded>().is_some());
+        assert_eq!(manager.client.reauthorizations(), 0);
+
+        let manager = Mutex::new(manager);
+        let pending = SpotifyManager::with_consent(&manager, |manager| Box::pin(manager.toggle_like_current_track())).await.unwrap();
+        assert!(pending.liked);
+        assert_eq!(manager.lock().await.client.reauthorizations(), 1);
+    }
+
+    #[tokio::test]
+    async fn toggle_playback_pauses_and_resumes() {
+        let mut manager = manager(playing(), 3);
+
+        assert!(!manager.toggle_playback().await.unwrap());
+        assert!(!manager.client.is_playing());
+        assert!(manager.toggle_playback().await.unwrap());
+        assert_eq!(manager.client.player_commands(), ["pause", "play"]);
+    }
+
+    #[tokio::test]
+    async fn toggle_playback_resumes_when_paused() {
+        let mut manager = manager(playing().paused(), 3);
+
+        assert!(manager.toggle_playback().await.unwrap());
+        assert!(manager.client.is_playing());
+    }
+
+    #[tokio::test]
+    async fn skipping_sends_player_commands() {
+        let mut manager = manager(playing(), 3);
+
+        manager.next_track().await.unwrap();
+        manager.previous_track().await.unwrap();
+
+        assert_eq!(manager.client.player_commands(), ["next", "previous"]);
+        assert_eq!(manager.stats().summary().latency["next"].calls, 1);
+    }
+
+    #[tokio::test]
+    async fn volume_changes_are_clamped() {
+        let mut manager = manager(playing().with_volume(95), 3);
+
+        assert_eq!(manager.change_volume(10).await.unwrap(), 100);
+        assert_eq!(manager.change_volume(-30).await.unwrap(), 70);
+        assert_eq!(manager.client.volume(), Some(70));
+    }
+
+    #[tokio::test]
+    async fn unmute_restores_the_previous_volume() {
+        let mut manager = manager(playing().with_volume(35), 3);
+
+        assert_eq!(manager.toggle_mute().await.unwrap(), 0);
+        assert_eq!(manager.toggle_mute().await.unwrap(), 35);
+        assert_eq!(manager.client.volume(), Some(35));
+    }
+
+    #[tokio::test]
+    async fn volume_needs_an_active_device() {
+        let mut manager = manager(FakeSpotifyApi::new(), 3);
+
+        let error = manager.change_volume(10).await.unwrap_err();
+
+        assert!(error.downcast_ref::<NoActiveDevice>().is_some());
+        assert!(manager.client.player_commands().is_empty());
+    }
+
+    #[tokio::test]
+    async fn seeks_start_from_the_current_progress_and_stop_at_the_end() {
+        let mut manager = manager(playing().at_progress(60_000), 3);
+
+        assert_eq!(manager.seek_by(-15_000).await.unwrap().position_ms, 45_000);
+        assert_eq!(manager.client.progress_ms(), Some(45_000));
+
+        // The fake track is three minutes long
+        assert_eq!(manager.seek_by(600_000).await.unwrap().position_ms, 180_000);
+        assert_eq!(manager.restart_track().await.unwrap().position_ms, 0);
+        assert_eq!(manager.client.player_commands(), ["seek", "seek", "seek"]);
+    }
+
+    #[tokio::test]
+    async fn shuffle_toggles_and_repeat_cycles() {
+        let mut manager = manager(playing(), 3);
+
+        assert!(manager.toggle_shuffle().await.unwrap().shuffle);
+        assert_eq!(manager.cycle_repeat().await.unwrap().repeat, RepeatState::Context);
+        assert_eq!(manager.cycle_repeat().await.unwrap().repeat, RepeatState::Track);
+
+        assert_eq!(manager.player_modes().await.unwrap(), Some(PlayerModes { shuffle: true, repeat: RepeatState::Track }));
+        assert_eq!(manager.client.player_commands(), ["shuffle", "repeat", "repeat"]);
+    }
+
+    #[tokio::test]
+    async fn startup_moves_to_the_preferred_device_only_when_idle() {
+        let mut idle = manager(FakeSpotifyApi::new().with_device("office", "Office Speaker"), 3);
+
+        let device = idle.transfer_to_preferred_device("office speaker").await.unwrap();
+        assert_eq!(device.map(|device| device.name).as_deref(), Some("Office Speaker"));
+        assert_eq!(idle.client.active_device().as_deref(), Some("office"));
+
+        let mut busy = manager(playing().with_device("office", "Office Speaker"), 3);
+        assert!(busy.transfer_to_preferred_device("office").await.unwrap().is_none());
+        assert_eq!(busy.client.active_device().as_deref(), Some("fake-device"));
+    }
+
+    #[tokio::test]
+    async fn unknown_preferred_device_is_an_error() {
+        let mut manager = manager(FakeSpotifyApi::new().with_device("office", "Office Speaker"), 3);
+
+        assert!(manager.transfer_to_preferred_device("Kitchen").await.is_err());
+        assert!(manager.client.player_commands().is_empty());
+    }
+
+    #[tokio::test]
+    async fn offline_actions_need_a_recently_seen_track() {
+        let mut manager = manager(playing(), 3);
+        manager.get_current_track().await.unwrap();
+
+        assert!(manager.queue_action(QueuedAction::Like).is_ok());
+
+        manager.last_track = Some((track_info(), Instant::now() - LAST_TRACK_MAX_AGE - Duration::from_secs(1)));
+        assert!(manager.queue_action(QueuedAction::Unlike).is_err());
+        assert_eq!(manager.action_queue().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn offline_toggle_flips_the_queued_or_cached_status() {
+        let mut manager = manager(playing(), 3);
+        manager.get_current_track().await.unwrap();
+        assert!(manager.offline_toggle_action().is_err());
+
+        manager.liked_cache.set(TRACK_ID, true);
+        assert_eq!(manager.offline_toggle_action().unwrap(), QueuedAction::Unlike);
+
+        manager.queue_action(QueuedAction::Unlike).unwrap();
+        assert_eq!(manager.offline_toggle_action().unwrap(), QueuedAction::Like);
+    }
+}
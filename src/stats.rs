@@ -0,0 +1,310 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    sync::Mutex,
+};
+use tracing::warn;
+
+use crate::convergence::VerificationResult;
+use crate::token_store;
+
+const STATS_FILE: &str = "stats.jsonl";
+
+/// Samples kept on disk and in memory, older ones are dropped when the file is opened or outgrows it
+const MAX_SAMPLES: usize = 5000;
+
+/// How far past `MAX_SAMPLES` a running session records before trimming, so the file isn't rewritten on every sample
+const TRIM_SLACK: usize = MAX_SAMPLES / 10;
+
+/// One measurement, stored as a line of JSON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Sample {
+    /// A Spotify API call made for `operation` (e.g. "like", "check_liked", "verify_liked")
+    ApiCall {
+        at: DateTime<Utc>,
+        operation: String,
+        latency_ms: u64,
+        success: bool,
+    },
+    /// The read-back of a like or unlike
+    Verification {
+        at: DateTime<Utc>,
+        operation: String,
+        verified_after_ms: u64,
+        attempts: u32,
+        success: bool,
+    },
+}
+
+/// Latency percentiles of one kind of API call
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatencySummary {
+    pub calls: usize,
+    pub failed: usize,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+}
+
+/// Everything the Stats tray item shows
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StatsSummary {
+    /// Keyed by operation
+    pub latency: BTreeMap<String, LatencySummary>,
+    pub verifications: usize,
+    pub verification_failures: usize,
+    pub average_attempts: f64,
+}
+
+impl StatsSummary {
+    pub fn verification_failure_rate(&self) -> f64 {
+        if self.verifications == 0 {
+            return 0.0;
+        }
+        self.verification_failures as f64 / self.verifications as f64
+    }
+}
+
+impl fmt::Display for StatsSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.latency.is_empty() && self.verifications == 0 {
+            return f.write_str("No actions recorded yet");
+        }
+
+        for (operation, latency) in &self.latency {
+            writeln!(f, "⏱️ {}: p50 {}ms, p95 {}ms ({} calls, {} failed)",
+                operation, latency.p50_ms, latency.p95_ms, latency.calls, latency.failed)?;
+        }
+        write!(f, "🔍 Verification: {:.1}% failed, {:.1} attempts on average ({} checked)",
+            self.verification_failure_rate() * 100.0, self.average_attempts, self.verifications)
+    }
+}
+
+/// API latencies and verification outcomes, appended to a JSON lines file in the cache directory
+///
+/// Shared by all profiles. Recording never fails the action being measured: write
+/// errors are logged and the sample is kept in memory only.
+#[derive(Debug, Default)]
+pub struct Stats {
+    /// `None` keeps the samples in memory only
+    path: Option<PathBuf>,
+    samples: Mutex<Vec<Sample>>,
+}
+
+impl Stats {
+    pub fn open() -> Result<Self> {
+        Self::open_at(token_store::cache_dir()?.join(STATS_FILE))
+    }
+
+    pub fn open_at(path: PathBuf) -> Result<Self> {
+        let mut samples = Vec::new();
+        if path.exists() {
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read stats at {}", path.display()))?;
+            // Skip lines that don't parse (e.g. a write cut short) instead of losing the file
+            samples.extend(content.lines().filter_map(|line| serde_json::from_str::<Sample>(line).ok()));
+        }
+
+        if samples.len() > MAX_SAMPLES {
+            samples.drain(..samples.len() - MAX_SAMPLES);
+            rewrite(&path, &samples)?;
+        }
+
+        Ok(Self { path: Some(path), samples: Mutex::new(samples) })
+    }
+
+    /// Record how long an API call made for `operation` took
+    pub fn record_api_call(&self, operation: &str, latency_ms: u64, success: bool) {
+        self.record(Sample::ApiCall {
+            at: Utc::now(),
+            operation: operation.to_string(),
+            latency_ms,
+            success,
+        });
+    }
+
+    /// Record the outcome of verifying `operation`
+    pub fn record_verification<S>(&self, operation: &str, result: &VerificationResult<S>) {
+        self.record(Sample::Verification {
+            at: Utc::now(),
+            operation: operation.to_string(),
+            verified_after_ms: result.verified_after_ms,
+            attempts: result.attempts,
+            success: result.success,
+        });
+    }
+
+    fn record(&self, sample: Sample) {
+        let mut samples = self.samples.lock().unwrap();
+        if let Some(path) = &self.path {
+            if let Err(e) = append(path, &sample) {
+                warn!("⚠️ Failed to record stats: {}", e);
+            }
+        }
+        samples.push(sample);
+
+        if samples.len() > MAX_SAMPLES + TRIM_SLACK {
+            let excess = samples.len() - MAX_SAMPLES;
+            samples.drain(..excess);
+            if let Some(path) = &self.path {
+                if let Err(e) = rewrite(path, &samples) {
+                    warn!("⚠️ Failed to trim stats: {}", e);
+                }
+            }
+        }
+    }
+
+    pub fn summary(&self) -> StatsSummary {
+        let samples = self.samples.lock().unwrap();
+        let mut latencies: BTreeMap<String, (Vec<u64>, usize)> = BTreeMap::new();
+        let mut summary = StatsSummary::default();
+        let mut total_attempts = 0u64;
+
+        for sample in samples.iter() {
+            match sample {
+                Sample::ApiCall { operation, latency_ms, success, .. } => {
+                    let (values, failed) = latencies.entry(operation.clone()).or_default();
+                    values.push(*latency_ms);
+                    if !success {
+                        *failed += 1;
+                    }
+                }
+                Sample::Verification { attempts, success, .. } => {
+                    summary.verifications += 1;
+                    total_attempts += u64::from(*attempts);
+                    if !success {
+                        summary.verification_failures += 1;
+                    }
+                }
+            }
+        }
+
+        for (operation, (mut values, failed)) in latencies {
+            values.sort_unstable();
+            summary.latency.insert(operation, LatencySummary {
+                calls: values.len(),
+                failed,
+                p50_ms: percentile(&values, 50),
+                p95_ms: percentile(&values, 95),
+            });
+        }
+        if summary.verifications > 0 {
+            summary.average_attempts = total_attempts as f64 / summary.verifications as f64;
+        }
+        summary
+    }
+}
+
+fn append(path: &PathBuf, sample: &Sample) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .context("Failed to open stats file")?;
+    writeln!(file, "{}", serde_json::to_string(sample)?).context("Failed to write stats file")?;
+    Ok(())
+}
+
+fn rewrite(path: &PathBuf, samples: &[Sample]) -> Result<()> {
+    let content: String = samples.iter()
+        .filter_map(|sample| serde_json::to_string(sample).ok())
+        .map(|line| line + "\n")
+        .collect();
+    fs::write(path, content).context("Failed to trim stats file")
+}
+
+/// Nearest-rank percentile of sorted, non-empty values
+fn percentile(sorted: &[u64], percent: usize) -> u64 {
+    let rank = (sorted.len() * percent).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn verification(success: bool, attempts: u32) -> VerificationResult<&'static str> {
+        VerificationResult {
+            success,
+            subject: "Song",
+            verified_after_ms: 1000,
+            attempts,
+        }
+    }
+
+    #[test]
+    fn percentiles_use_nearest_rank() {
+        let values: Vec<u64> = (1..=100).collect();
+
+        assert_eq!(percentile(&values, 50), 50);
+        assert_eq!(percentile(&values, 95), 95);
+        assert_eq!(percentile(&[42], 95), 42);
+    }
+
+    #[test]
+    fn summary_groups_latency_by_operation() {
+        let stats = Stats::default();
+        for latency_ms in [80, 120, 100] {
+            stats.record_api_call("like", latency_ms, true);
+        }
+        stats.record_api_call("check_liked", 60, false);
+
+        let summary = stats.summary();
+
+        assert_eq!(summary.latency["like"], LatencySummary { calls: 3, failed: 0, p50_ms: 100, p95_ms: 120 });
+        assert_eq!(summary.latency["check_liked"].failed, 1);
+    }
+
+    #[test]
+    fn summary_reports_verification_failure_rate_and_attempts() {
+        let stats = Stats::default();
+        stats.record_verification("like", &verification(true, 1));
+        stats.record_verification("like", &verification(true, 2));
+        stats.record_verification("unlike", &verification(false, 8));
+        stats.record_verification("unlike", &verification(true, 1));
+
+        let summary = stats.summary();
+
+        assert_eq!(summary.verifications, 4);
+        assert_eq!(summary.verification_failure_rate(), 0.25);
+        assert_eq!(summary.average_attempts, 3.0);
+    }
+
+    #[test]
+    fn samples_survive_reopening() {
+        let path = std::env::temp_dir().join(format!("stats_test_{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let stats = Stats::open_at(path.clone()).unwrap();
+        stats.record_api_call("like", 90, true);
+        stats.record_verification("like", &verification(true, 1));
+        let reopened = Stats::open_at(path.clone()).unwrap();
+        let _ = fs::remove_file(&path);
+
+        let summary = reopened.summary();
+        assert_eq!(summary.latency["like"].calls, 1);
+        assert_eq!(summary.verifications, 1);
+    }
+
+    #[test]
+    fn long_sessions_are_trimmed_to_the_cap() {
+        let path = std::env::temp_dir().join(format!("stats_trim_test_{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let stats = Stats::open_at(path.clone()).unwrap();
+        for latency_ms in 0..(MAX_SAMPLES + TRIM_SLACK + 1) as u64 {
+            stats.record_api_call("like", latency_ms, true);
+        }
+        let lines = fs::read_to_string(&path).unwrap().lines().count();
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(stats.summary().latency["like"].calls, MAX_SAMPLES);
+        assert_eq!(lines, MAX_SAMPLES);
+    }
+}
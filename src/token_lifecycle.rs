@@ -0,0 +1,79 @@
+use anyhow::{Context, Result};
+use chrono::{Duration as ChronoDuration, Utc};
+use rspotify::{http::HttpError, ClientError};
+use tracing::{info, warn};
+
+use crate::spotify_api::SpotifyApi;
+
+/// Refresh this long before the access token actually expires
+const DEFAULT_REFRESH_MARGIN_SECS: i64 = 60;
+
+/// Owns the access token's lifetime: refreshes ahead of expiry and after a 401
+///
+/// Every refresh goes through the client's token callback, so the new token is
+/// persisted to the token store as soon as it arrives.
+#[derive(Debug, Clone)]
+pub struct TokenLifecycle {
+    refresh_margin: ChronoDuration,
+}
+
+impl Default for TokenLifecycle {
+    fn default() -> Self {
+        Self::new(ChronoDuration::seconds(DEFAULT_REFRESH_MARGIN_SECS))
+    }
+}
+
+impl TokenLifecycle {
+    pub fn new(refresh_margin: ChronoDuration) -> Self {
+        Self { refresh_margin }
+    }
+
+    /// Refresh the token if it expires within the margin; no network call otherwise
+    pub async fn ensure_fresh(&self, client: &impl SpotifyApi) -> Result<()> {
+        let expires_at = client.token().await
+            .and_then(|token| token.expires_at);
+
+        // Without an expiry we can't plan ahead, a 401 will trigger the refresh instead
+        if let Some(expires_at) = expires_at.filter(|t| *t - self.refresh_margin <= Utc::now()) {
+            info!("🔄 Access token expires at {}, refreshing ahead of time", expires_at);
+            client.refresh_token().await
+                .context("Failed to refresh access token")?;
+            info!("✅ Token refreshed successfully");
+        }
+
+        Ok(())
+    }
+
+    /// Refresh after the API rejected the access token
+    pub async fn refresh_after_unauthorized(&self, client: &impl SpotifyApi) -> Result<()> {
+        warn!("🔄 Spotify rejected the access token (401), refreshing...");
+        client.refresh_token().await
+            .context("Failed to refresh token after 401 Unauthorized")?;
+        info!("✅ Token refreshed successfully");
+        Ok(())
+    }
+
+    /// Whether a failed refresh was turned down by Spotify (e.g. a revoked refresh token), not cut short by the network
+    pub fn is_refresh_rejected(error: &anyhow::Error) -> bool {
+        match error.downcast_ref::<ClientError>() {
+            Some(ClientError::Http(http)) => match http.as_ref() {
+                HttpError::StatusCode(response) => matches!(response.status().as_u16(), 400 | 401),
+                _ => false,
+            },
+            Some(ClientError::InvalidToken) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether an API error is a 401, i.e. the access token is no longer accepted
+    pub fn is_unauthorized(error: &ClientError) -> bool {
+        match error {
+            ClientError::Http(http) => match http.as_ref() {
+                HttpError::StatusCode(response) => response.status().as_u16() == 401,
+                _ => false,
+            },
+            ClientError::InvalidToken => true,
+            _ => false,
+        }
+    }
+}
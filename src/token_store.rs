@@ -0,0 +1,537 @@
+use anyhow::{anyhow, Context, Result};
+use argon2::Argon2;
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use rspotify::{CallbackError, Token, TokenCallback};
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use tracing::{info, warn};
+
+use crate::config::{EncryptionKeySource, TokenBackend, TokenStorageConfig, DEFAULT_PROFILE};
+
+/// Environment variable holding the passphrase for the encrypted token file
+pub const PASSPHRASE_ENV_VAR: &str = "SPOTIFY_QUICK_ACTIONS_PASSPHRASE";
+
+const TOKEN_FILE_STEM: &str = "spotify_token";
+const KEYRING_SERVICE: &str = "spotify-quick-actions";
+const KEYRING_USER: &str = "spotify-token";
+/// Random key used instead of the machine ID where that can't be read
+const KEY_FILE_NAME: &str = "token_key";
+const KEY_FILE_LEN: usize = 32;
+
+/// Secrets already read this session, so a passphrase is only asked for once
+static SESSION_SECRETS: Mutex<Vec<(EncryptionKeySource, EncryptionSecret)>> = Mutex::new(Vec::new());
+
+/// Header identifying our encrypted token format (bumped if the layout changes)
+const ENCRYPTED_MAGIC: &[u8; 5] = b"SQAT1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Persistent storage for the Spotify OAuth token
+pub trait TokenStore: Send + Sync {
+    /// Load the stored token, `None` if nothing has been stored yet
+    fn load(&self) -> Result<Option<Token>>;
+    /// Store (or replace) the token
+    fn save(&self, token: &Token) -> Result<()>;
+    /// Remove the stored token, succeeding if there was none
+    fn clear(&self) -> Result<()>;
+    /// Human readable location, used in status output
+    fn location(&self) -> String;
+}
+
+/// Open the configured token store for a profile, migrating a legacy plaintext cache into it
+pub fn open(config: &TokenStorageConfig, profile: &str) -> Result<Arc<dyn TokenStore>> {
+    let store: Arc<dyn TokenStore> = match config.backend {
+        TokenBackend::Plaintext => Arc::new(PlaintextFileStore::new(
+            cache_dir()?.join(format!("{}.json", profile_key(TOKEN_FILE_STEM, profile))),
+        )),
+        TokenBackend::Encrypted => Arc::new(EncryptedFileStore::new(
+            cache_dir()?.join(format!("{}.enc", profile_key(TOKEN_FILE_STEM, profile))),
+            session_secret(config.encryption_key)?,
+        )),
+        TokenBackend::Keyring => Arc::new(KeyringStore::new(&profile_key(KEYRING_USER, profile))?),
+    };
+
+    if config.backend != TokenBackend::Plaintext {
+        let legacy = cache_dir()?.join(format!("{}.json", profile_key(TOKEN_FILE_STEM, profile)));
+        migrate_plaintext_cache(store.as_ref(), &legacy)?;
+    }
+
+    Ok(store)
+}
+
+/// The default profile keeps the original names so existing caches stay valid
+pub fn profile_key(base: &str, profile: &str) -> String {
+    if profile == DEFAULT_PROFILE {
+        base.to_string()
+    } else {
+        format!("{}_{}", base, profile)
+    }
+}
+
+/// Callback for rspotify that persists every newly requested or refreshed token
+pub fn persist_callback(store: Arc<dyn TokenStore>) -> TokenCallback {
+    TokenCallback(Box::new(move |token: Token| {
+        store
+            .save(&token)
+            .map_err(|e| CallbackError::CustomizedError(format!("Failed to persist token: {:#}", e)))
+    }))
+}
+
+/// Move an existing plaintext `spotify_token.json` into the chosen store
+fn migrate_plaintext_cache(store: &dyn TokenStore, legacy: &Path) -> Result<()> {
+    let legacy = PlaintextFileStore::new(legacy.to_path_buf());
+
+    let token = match legacy.load() {
+        Ok(Some(token)) => token,
+        Ok(None) => return Ok(()),
+        Err(e) => {
+            warn!("⚠️ Ignoring unreadable plaintext token cache: {}", e);
+            return legacy.clear();
+        }
+    };
+
+    store.save(&token)
+        .context("Failed to migrate plaintext token cache")?;
+    legacy.clear()?;
+    info!("🔐 Migrated plaintext token cache to {}", store.location());
+
+    Ok(())
+}
+
+/// Application cache directory, created on demand
+pub fn cache_dir() -> Result<PathBuf> {
+    let cache_dir = dirs::cache_dir()
+        .context("Failed to get system cache directory")?;
+
+    let app_cache_dir = cache_dir.join("spotify-quick-actions");
+    fs::create_dir_all(&app_cache_dir)
+        .context("Failed to create application cache directory")?;
+
+    Ok(app_cache_dir)
+}
+
+/// Secret material the encryption key is derived from, and where it came from for error messages
+#[derive(Clone)]
+pub struct EncryptionSecret {
+    bytes: Vec<u8>,
+    source: String,
+}
+
+impl EncryptionSecret {
+    pub fn new(bytes: Vec<u8>, source: impl Into<String>) -> Self {
+        Self { bytes, source: source.into() }
+    }
+}
+
+/// The encryption secret for this session, read on first use
+///
+/// Every `open` (startup, profile switches, cache status) goes through here,
+/// the lock also keeps two of them from prompting at the same time.
+fn session_secret(source: EncryptionKeySource) -> Result<EncryptionSecret> {
+    let mut secrets = SESSION_SECRETS.lock().unwrap();
+    if let Some((_, secret)) = secrets.iter().find(|(cached, _)| *cached == source) {
+        return Ok(secret.clone());
+    }
+
+    let secret = encryption_secret(source)?;
+    secrets.push((source, secret.clone()));
+    Ok(secret)
+}
+
+/// Read the configured encryption secret
+///
+/// The machine ID is readable by every local user, so that key only stops the
+/// file from being decrypted on another machine.
+fn encryption_secret(source: EncryptionKeySource) -> Result<EncryptionSecret> {
+    match source {
+        EncryptionKeySource::Machine => match machine_uid::get() {
+            Ok(machine_id) => Ok(EncryptionSecret::new(
+                format!("{}:{}", KEYRING_SERVICE, machine_id).into_bytes(),
+                source.to_string(),
+            )),
+            Err(e) => {
+                let key_file = cache_dir()?.join(KEY_FILE_NAME);
+                warn!("⚠️ Failed to read machine ID ({}), encrypting the token cache with the key in {} instead",
+                    e, key_file.display());
+                Ok(EncryptionSecret::new(
+                    load_or_create_key_file(&key_file)?,
+                    format!("key file {}", key_file.display()),
+                ))
+            }
+        },
+        EncryptionKeySource::Passphrase => {
+            if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV_VAR) {
+                if !passphrase.is_empty() {
+                    return Ok(EncryptionSecret::new(passphrase.into_bytes(), source.to_string()));
+                }
+            }
+
+            print!("Enter token cache passphrase: ");
+            io::stdout().flush().context("Failed to flush stdout")?;
+
+            let mut input = String::new();
+            io::stdin()
+                .read_line(&mut input)
+                .context("Failed to read passphrase")?;
+
+            let passphrase = input.trim_end_matches(['\r', '\n']).to_string();
+            if passphrase.is_empty() {
+                return Err(anyhow!(
+                    "No token cache passphrase given. Set {} or enter it when prompted.",
+                    PASSPHRASE_ENV_VAR
+                ));
+            }
+
+            Ok(EncryptionSecret::new(passphrase.into_bytes(), source.to_string()))
+        }
+    }
+}
+
+/// Random key kept next to the token cache, created on first use
+fn load_or_create_key_file(path: &Path) -> Result<Vec<u8>> {
+    match fs::read(path) {
+        Ok(key) if key.len() == KEY_FILE_LEN => return Ok(key),
+        Ok(_) => return Err(anyhow!("Token key file {} is corrupted", path.display())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e).with_context(|| format!("Failed to read token key file {}", path.display())),
+    }
+
+    let mut key = vec![0u8; KEY_FILE_LEN];
+    OsRng.fill_bytes(&mut key);
+    write_private(path, &key)
+        .with_context(|| format!("Failed to write token key file {}", path.display()))?;
+
+    Ok(key)
+}
+
+/// The original token cache: `spotify_token.json` in plain text
+pub struct PlaintextFileStore {
+    path: PathBuf,
+}
+
+impl PlaintextFileStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+impl TokenStore for PlaintextFileStore {
+    fn load(&self) -> Result<Option<Token>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&self.path)
+            .context("Failed to read token cache file")?;
+        let token = serde_json::from_str(&content)
+            .context("Token cache file is corrupted")?;
+
+        Ok(Some(token))
+    }
+
+    fn save(&self, token: &Token) -> Result<()> {
+        let content = serde_json::to_string(token)
+            .context("Failed to serialize token")?;
+        write_private(&self.path, content.as_bytes())
+            .context("Failed to write token cache file")
+    }
+
+    fn clear(&self) -> Result<()> {
+        remove_file_if_exists(&self.path)
+    }
+
+    fn location(&self) -> String {
+        self.path.display().to_string()
+    }
+}
+
+/// Token cache encrypted with ChaCha20-Poly1305 under an Argon2-derived key
+pub struct EncryptedFileStore {
+    path: PathBuf,
+    secret: EncryptionSecret,
+}
+
+impl EncryptedFileStore {
+    pub fn new(path: PathBuf, secret: EncryptionSecret) -> Self {
+        Self { path, secret }
+    }
+
+    fn cipher(&self, salt: &[u8]) -> Result<ChaCha20Poly1305> {
+        let mut key = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(&self.secret.bytes, salt, &mut key)
+            .map_err(|e| anyhow!("Failed to derive token encryption key: {}", e))?;
+
+        Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+    }
+}
+
+impl TokenStore for EncryptedFileStore {
+    fn load(&self) -> Result<Option<Token>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+
+        let data = fs::read(&self.path)
+            .with_context(|| format!("Failed to read encrypted token cache {}", self.path.display()))?;
+
+        let header_len = ENCRYPTED_MAGIC.len() + SALT_LEN + NONCE_LEN;
+        if data.len() < header_len || !data.starts_with(ENCRYPTED_MAGIC) {
+            return Err(anyhow!(
+                "Encrypted token cache {} has an unknown format (truncated or not written by this app)",
+                self.path.display()
+            ));
+        }
+
+        let (salt, rest) = data[ENCRYPTED_MAGIC.len()..].split_at(SALT_LEN);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+        let plaintext = self.cipher(salt)?
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow!(
+                "Failed to decrypt token cache {} with the key from the {} - wrong passphrase or different machine?",
+                self.path.display(),
+                self.secret.source
+            ))?;
+
+        let token = serde_json::from_slice(&plaintext)
+            .with_context(|| format!("Decrypted token cache {} is corrupted", self.path.display()))?;
+
+        Ok(Some(token))
+    }
+
+    fn save(&self, token: &Token) -> Result<()> {
+        let plaintext = serde_json::to_vec(token)
+            .context("Failed to serialize token")?;
+
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+        let ciphertext = self.cipher(&salt)?
+            .encrypt(&nonce, plaintext.as_slice())
+            .map_err(|_| anyhow!("Failed to encrypt token"))?;
+
+        let mut data = Vec::with_capacity(ENCRYPTED_MAGIC.len() + SALT_LEN + NONCE_LEN + ciphertext.len());
+        data.extend_from_slice(ENCRYPTED_MAGIC);
+        data.extend_from_slice(&salt);
+        data.extend_from_slice(&nonce);
+        data.extend_from_slice(&ciphertext);
+
+        write_private(&self.path, &data)
+            .context("Failed to write encrypted token cache")
+    }
+
+    fn clear(&self) -> Result<()> {
+        remove_file_if_exists(&self.path)
+    }
+
+    fn location(&self) -> String {
+        format!("{} (encrypted)", self.path.display())
+    }
+}
+
+/// Token kept in the OS keyring (Secret Service on Linux, Credential Manager on Windows)
+pub struct KeyringStore {
+    entry: keyring::Entry,
+    user: String,
+}
+
+impl KeyringStore {
+    pub fn new(user: &str) -> Result<Self> {
+        let entry = keyring::Entry::new(KEYRING_SERVICE, user)
+            .context("Failed to open OS keyring entry")?;
+        Ok(Self { entry, user: user.to_string() })
+    }
+}
+
+impl TokenStore for KeyringStore {
+    fn load(&self) -> Result<Option<Token>> {
+        match self.entry.get_password() {
+            Ok(content) => {
+                let token = serde_json::from_str(&content)
+                    .context("Token stored in keyring is corrupted")?;
+                Ok(Some(token))
+            }
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(anyhow!("Failed to read token from keyring: {}", e)),
+        }
+    }
+
+    fn save(&self, token: &Token) -> Result<()> {
+        let content = serde_json::to_string(token)
+            .context("Failed to serialize token")?;
+        self.entry
+            .set_password(&content)
+            .map_err(|e| anyhow!("Failed to store token in keyring: {}", e))
+    }
+
+    fn clear(&self) -> Result<()> {
+        match self.entry.delete_password() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(anyhow!("Failed to remove token from keyring: {}", e)),
+        }
+    }
+
+    fn location(&self) -> String {
+        format!("OS keyring ({}/{})", KEYRING_SERVICE, self.user)
+    }
+}
+
+/// Write a file only the current user can read, token files hold the refresh token
+fn write_private(path: &Path, data: &[u8]) -> io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(path)?;
+    // The mode only applies to new files, tighten caches written by older versions too
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(data)
+}
+
+fn remove_file_if_exists(path: &PathBuf) -> Result<()> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e).with_context(|| format!("Failed to remove {}", path.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("token_store_{}_{}", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn encrypted(path: &Path, passphrase: &str) -> EncryptedFileStore {
+        EncryptedFileStore::new(path.to_path_buf(), EncryptionSecret::new(passphrase.as_bytes().to_vec(), "passphrase"))
+    }
+
+    fn token() -> Token {
+        Token {
+            access_token: "access".to_string(),
+            refresh_token: Some("refresh".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn encrypted_token_round_trips() {
+        let path = temp_path("round_trip.enc");
+        let store = encrypted(&path, "secret");
+        let token = token();
+
+        store.save(&token).unwrap();
+        let loaded = store.load();
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(loaded.unwrap(), Some(token));
+    }
+
+    #[test]
+    fn wrong_key_fails_and_leaves_the_file_untouched() {
+        let path = temp_path("wrong_key.enc");
+        encrypted(&path, "secret").save(&token()).unwrap();
+        let before = fs::read(&path).unwrap();
+
+        let err = encrypted(&path, "other").load().unwrap_err().to_string();
+        let after = fs::read(&path).unwrap();
+        let _ = fs::remove_file(&path);
+
+        assert!(err.contains(&path.display().to_string()), "{}", err);
+        assert!(err.contains("passphrase"), "{}", err);
+        assert_eq!(before, after);
+    }
+
+    #[test]
+    fn truncated_or_foreign_files_are_rejected() {
+        let path = temp_path("bad_format.enc");
+        let store = encrypted(&path, "secret");
+
+        fs::write(&path, &ENCRYPTED_MAGIC[..]).unwrap();
+        let truncated = store.load().unwrap_err().to_string();
+        fs::write(&path, [0u8; 64]).unwrap();
+        let bad_magic = store.load().unwrap_err().to_string();
+        let _ = fs::remove_file(&path);
+
+        assert!(truncated.contains("unknown format"), "{}", truncated);
+        assert!(bad_magic.contains("unknown format"), "{}", bad_magic);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn token_files_are_only_readable_by_the_owner() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = temp_path("private.enc");
+        fs::write(&path, b"old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        encrypted(&path, "secret").save(&token()).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn fallback_key_file_is_reused() {
+        let path = temp_path("token_key");
+
+        let created = load_or_create_key_file(&path).unwrap();
+        let reused = load_or_create_key_file(&path).unwrap();
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(created.len(), KEY_FILE_LEN);
+        assert_eq!(created, reused);
+    }
+
+    #[test]
+    fn passphrase_is_read_once_per_session() {
+        std::env::set_var(PASSPHRASE_ENV_VAR, "first");
+        let first = session_secret(EncryptionKeySource::Passphrase).unwrap();
+        std::env::set_var(PASSPHRASE_ENV_VAR, "second");
+        let second = session_secret(EncryptionKeySource::Passphrase).unwrap();
+        std::env::remove_var(PASSPHRASE_ENV_VAR);
+
+        assert_eq!(first.bytes, b"first");
+        assert_eq!(second.bytes, b"first");
+    }
+
+    #[test]
+    fn plaintext_cache_is_migrated_and_removed() {
+        let legacy = temp_path("legacy.json");
+        let path = temp_path("migrated.enc");
+        let token = token();
+        PlaintextFileStore::new(legacy.clone()).save(&token).unwrap();
+        let store = encrypted(&path, "secret");
+
+        migrate_plaintext_cache(&store, &legacy).unwrap();
+        let loaded = store.load();
+        let legacy_left = legacy.exists();
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(loaded.unwrap(), Some(token));
+        assert!(!legacy_left);
+    }
+}